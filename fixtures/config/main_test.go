@@ -0,0 +1,6 @@
+package main
+
+import "testing"
+
+func TestConfig(t *testing.T) {
+}
@@ -0,0 +1,2 @@
+def test_config():
+    assert 1 == 1
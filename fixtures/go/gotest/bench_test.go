@@ -0,0 +1,16 @@
+package main
+
+import "testing"
+
+func BenchmarkParse(b *testing.B) {
+	for i := 0; i < b.N; i++ {
+		parseInput("GET https://meain.io")
+	}
+}
+
+func FuzzParse(f *testing.F) {
+	f.Add("GET https://meain.io")
+	f.Fuzz(func(t *testing.T, input string) {
+		parseInput(input)
+	})
+}
@@ -0,0 +1,17 @@
+package main
+
+import "testing"
+
+func TestSubtests(t *testing.T) {
+	t.Run("simple case", func(t *testing.T) {
+		if 1 != 1 {
+			t.Fail()
+		}
+	})
+
+	t.Run("with (parens)+", func(t *testing.T) {
+		t.Run(`nested one`, func(t *testing.T) {
+			t.Log("inside")
+		})
+	})
+}
@@ -0,0 +1,11 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/suite"
+)
+
+func TestOtherSuite(t *testing.T) {
+	suite.Run(t, &OtherSuite{})
+}
@@ -0,0 +1,23 @@
+package main
+
+import (
+	"testing"
+
+	"github.com/stretchr/testify/suite"
+)
+
+type MySuite struct{ suite.Suite }
+
+type OtherSuite struct{ suite.Suite }
+
+func (s *MySuite) TestFoo() {
+	s.Equal(1, 1)
+}
+
+func (s *OtherSuite) TestBar() {
+	s.True(true)
+}
+
+func TestMySuite(t *testing.T) {
+	suite.Run(t, new(MySuite))
+}
@@ -0,0 +1,36 @@
+package main
+
+import "testing"
+
+func TestTable(t *testing.T) {
+	tests := []struct {
+		name  string
+		input string
+	}{
+		{name: "empty input", input: ""},
+		{
+			name:  "with spaces",
+			input: "a b",
+		},
+	}
+	for _, tc := range tests {
+		t.Run(tc.name, func(t *testing.T) {
+			if len(tc.input) > 3 {
+				t.Fail()
+			}
+		})
+	}
+}
+
+func TestMap(t *testing.T) {
+	tests := map[string]struct {
+		input string
+	}{
+		"upper case": {input: "A"},
+	}
+	for name, tc := range tests {
+		t.Run(name, func(t *testing.T) {
+			_ = tc.input
+		})
+	}
+}
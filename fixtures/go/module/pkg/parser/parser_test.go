@@ -0,0 +1,9 @@
+//go:build integration && postgres && (linux || darwin) && !windows
+
+package parser
+
+import "testing"
+
+func TestParse(t *testing.T) {
+	t.Log("parse")
+}
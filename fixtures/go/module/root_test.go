@@ -0,0 +1,7 @@
+package module
+
+import "testing"
+
+func TestRoot(t *testing.T) {
+	t.Log("root")
+}
@@ -0,0 +1,6 @@
+from django.test import TestCase
+
+
+class TestViews(TestCase):
+    def test_index(self):
+        self.assertTrue(True)
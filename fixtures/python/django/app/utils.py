@@ -0,0 +1,6 @@
+def slugify(text):
+    """
+    >>> slugify("Hello World")
+    'hello-world'
+    """
+    return text.lower().replace(" ", "-")
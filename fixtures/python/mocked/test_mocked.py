@@ -0,0 +1,6 @@
+from unittest.mock import patch
+
+
+def test_patched():
+    with patch("os.getcwd", return_value="/"):
+        assert True
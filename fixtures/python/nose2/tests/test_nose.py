@@ -0,0 +1,6 @@
+import unittest
+
+
+class TestNose(unittest.TestCase):
+    def test_nose(self):
+        self.assertTrue(True)
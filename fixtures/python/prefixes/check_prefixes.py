@@ -0,0 +1,18 @@
+import pytest
+
+
+class SuiteMath:
+    def check_add(self):
+        assert 1 + 1 == 2
+
+    def helper(self):
+        pass
+
+
+@pytest.mark.slow
+@pytest.mark.skipif(
+    False,
+    reason="never",
+)
+def addition_spec():
+    assert True
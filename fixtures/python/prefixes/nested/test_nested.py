@@ -0,0 +1,2 @@
+def should_work():
+    assert True
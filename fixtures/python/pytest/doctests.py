@@ -0,0 +1,30 @@
+"""Helpers with examples
+
+>>> add(1, 2)
+3
+"""
+
+
+def add(a, b):
+    """Add two numbers
+
+    >>> add(2, 2)
+    4
+    """
+    return a + b
+
+
+class Counter:
+    """Counts things"""
+
+    def increment(self, n):
+        '''
+        >>> Counter().increment(1)
+        1
+        '''
+        return n
+
+
+def no_examples():
+    """Nothing to run here"""
+    return None
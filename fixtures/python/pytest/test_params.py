@@ -0,0 +1,56 @@
+import pytest
+
+
+@pytest.mark.parametrize(
+    "value,expected",
+    [
+        (1, 2),
+        ("a", "b"),
+        pytest.param(3, 4, id="three"),
+        ([1], 2),
+        (compute(), 5),
+    ],
+)
+def test_increment(value, expected):
+    assert value
+
+
+@pytest.mark.parametrize(
+    "flag",
+    [
+        True,
+        None,
+    ],
+    ids=["on", "off"],
+)
+def test_flag(flag):
+    assert flag or not flag
+
+
+class TestParams:
+    @pytest.mark.parametrize("n", [
+        1.50,
+        -2,  # negative
+    ])
+    def test_number(self, n):
+        assert n
+
+
+@pytest.mark.parametrize(
+    "v",
+    [
+        1,
+        2,
+    ],
+    ids=lambda v: f"case{v}",
+)
+def test_ids_function(v):
+    assert v
+
+
+@pytest.mark.parametrize("a", [
+    3,
+])
+@pytest.mark.parametrize("b", [1, 2])
+def test_stacked(a, b):
+    assert a and b
@@ -0,0 +1,19 @@
+import pytest
+
+
+class TestSyntax:
+    def helper(self):
+        pass
+
+    @pytest.mark.slow
+    @pytest.mark.parametrize("x", [1, 2])
+    def test_decorated(self, x):
+        assert x
+
+
+def test_multiline(
+    a,
+    b,
+):
+    # def test_in_comment():
+    assert a == b
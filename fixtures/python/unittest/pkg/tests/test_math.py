@@ -0,0 +1,9 @@
+import unittest
+
+
+class TestMath(unittest.TestCase):
+    def test_add(self):
+        self.assertEqual(1 + 1, 2)
+
+    def test_sub(self):
+        self.assertEqual(1 - 1, 0)
@@ -0,0 +1,10 @@
+fn main() {}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_tool() {
+        assert!(true);
+    }
+}
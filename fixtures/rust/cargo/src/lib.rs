@@ -0,0 +1,10 @@
+#[path = "platform/linux_impl.rs"]
+mod platform;
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_lib() {
+        assert!(true);
+    }
+}
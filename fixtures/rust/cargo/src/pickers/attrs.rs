@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn handles_timeout() {
+        assert!(true);
+    }
+
+    #[test]
+    #[ignore]
+    #[should_panic(expected = "boom")]
+    pub fn panics_loudly() {
+        panic!("boom");
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    fn cases(#[case] x: u32) {
+        assert!(x > 0);
+    }
+
+    #[my_framework::check]
+    fn custom_attribute() {
+        assert!(true);
+    }
+}
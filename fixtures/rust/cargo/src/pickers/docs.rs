@@ -0,0 +1,27 @@
+/// Adds two numbers
+///
+/// ```
+/// assert_eq!(toffee::pickers::docs::add(1, 2), 3);
+/// ```
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+pub struct Counter;
+
+impl Counter {
+    /// ```
+    /// let c = Counter::new();
+    /// ```
+    #[inline]
+    pub fn new() -> Counter {
+        Counter
+    }
+
+    /// ```rust
+    /// assert_eq!(Counter::new().count(), 0);
+    /// ```
+    pub fn count(&self) -> usize {
+        0
+    }
+}
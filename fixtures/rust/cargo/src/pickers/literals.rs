@@ -0,0 +1,12 @@
+#[cfg(test)]
+mod tests {
+    mod inner {
+        #[test]
+        fn test_brace_char() {
+            assert_eq!('{', "{".chars().next().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_after() {}
+}
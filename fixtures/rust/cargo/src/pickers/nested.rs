@@ -0,0 +1,19 @@
+pub mod parser {
+    pub fn parse() {}
+
+    #[cfg(test)]
+    mod tests {
+        mod parsing {
+            #[test]
+            fn test_parse() {
+                super::super::parse();
+            }
+
+            #[test]
+            fn test_parse_empty() {}
+        }
+
+        #[test]
+        fn test_after_nested() {}
+    }
+}
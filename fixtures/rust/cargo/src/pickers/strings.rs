@@ -0,0 +1,12 @@
+#[cfg(test)]
+mod tests {
+    const DATA: &str = "{
+  \"key\": 1
+}";
+
+    /* a stray } in a comment */
+    #[test]
+    fn parses() {
+        assert!(DATA.contains("key"));
+    }
+}
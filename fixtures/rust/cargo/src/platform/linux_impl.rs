@@ -0,0 +1,7 @@
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_linux() {
+        assert!(true);
+    }
+}
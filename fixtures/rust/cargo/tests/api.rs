@@ -0,0 +1,9 @@
+#[test]
+fn test_login() {
+    assert!(true);
+}
+
+#[test]
+fn test_logout() {
+    assert!(true);
+}
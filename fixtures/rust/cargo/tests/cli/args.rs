@@ -0,0 +1,4 @@
+#[test]
+fn test_flags() {
+    assert!(true);
+}
@@ -0,0 +1,11 @@
+pub fn run() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        run();
+    }
+}
@@ -0,0 +1,204 @@
+use crate::pickers::{CargoRunner, Command, Environment};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CONFIG_FILE: &str = ".toffee.toml";
+
+/// Overrides for a single language. Placeholders available in
+/// `template` are `{runner}`, `{flags}`, `{args}`, `{file}`,
+/// `{namespace}`, `{test}` and `{root}`, with `{file}` relative to the
+/// directory the command is run from.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageConfig {
+    pub runner: Option<String>,
+    #[serde(default)]
+    pub flags: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    pub template: Option<String>,
+    /// extra attributes that mark a function as a test (rust)
+    #[serde(default)]
+    pub test_attributes: Vec<String>,
+    /// duration to run fuzz targets for (go)
+    pub fuzz_time: Option<String>,
+    /// tool to run the tests with instead of the one detected (python)
+    pub environment: Option<Environment>,
+    /// `cargo` or `nextest` instead of the one detected (rust)
+    pub framework: Option<CargoRunner>,
+}
+
+/// Project level config read from `.toffee.toml`. Each table in the
+/// file is the config for the language with the same name.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub languages: HashMap<String, LanguageConfig>,
+    /// runner passed in from the command line, takes precedence
+    /// over the one in the config file
+    #[serde(skip)]
+    pub runner: Option<String>,
+}
+
+fn find_config(filename: &str) -> Option<PathBuf> {
+    let path = fs::canonicalize(filename).unwrap_or_else(|_| PathBuf::from(filename));
+    path.ancestors()
+        .map(|p| p.join(CONFIG_FILE))
+        .find(|p| p.is_file())
+}
+
+fn render(template: &str, command: &Command) -> Vec<String> {
+    let namespace = command.namespace_path().join("::");
+    let test = command
+        .test
+        .as_ref()
+        .map(|x| x.name.to_string())
+        .unwrap_or_default();
+    let mut argv = vec![];
+    for token in template.split_whitespace() {
+        match token {
+            "{runner}" => argv.extend(command.runner.iter().cloned()),
+            "{flags}" => argv.extend(command.flags.iter().cloned()),
+            "{args}" => argv.extend(command.args.iter().cloned()),
+            _ => {
+                let value = token
+                    .replace("{file}", &command.file_from_cwd())
+                    .replace("{namespace}", &namespace)
+                    .replace("{test}", &test)
+                    .replace("{root}", &command.root);
+                if !value.is_empty() {
+                    argv.push(value);
+                }
+            }
+        }
+    }
+    argv
+}
+
+impl Config {
+    pub fn parse(path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Unable to parse {}", path.display()))
+    }
+
+    /// Load the config file closest to the given file
+    pub fn load(filename: &str) -> Result<Config> {
+        match find_config(filename) {
+            Some(path) => Config::parse(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn language(&self, name: &str) -> LanguageConfig {
+        self.languages.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Update the command generated by a picker with user overrides
+    pub fn apply(&self, language: &str, command: &mut Command) {
+        let lc = self.language(language);
+        if let Some(runner) = self.runner.as_ref().or(lc.runner.as_ref()) {
+            command.runner = runner.split_whitespace().map(|x| x.to_string()).collect();
+        }
+        command.flags.extend(lc.flags);
+        command.env.extend(lc.env);
+        if let Some(template) = lc.template {
+            command.runner = render(&template, command);
+            command.flags.clear();
+            command.args.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pickers::Location;
+
+    fn sample_command() -> Command {
+        let mut comm = Command::new("pytest", "/project", "tests/test_stuff.py")
+            .arg("tests/test_stuff.py::TestClass::test_method");
+        comm.namespace = vec![Location {
+            name: "TestClass".to_string(),
+            line: 1,
+        }];
+        comm.test = Some(Location {
+            name: "test_method".to_string(),
+            line: 2,
+        });
+        comm
+    }
+
+    #[test]
+    fn test_find_config() {
+        let path = find_config("./fixtures/config/test_config.py").unwrap();
+        assert!(path.ends_with("fixtures/config/.toffee.toml"));
+        assert!(find_config("./fixtures/python/pytest/test_stuff.py").is_none());
+    }
+
+    #[test]
+    fn test_apply_runner_and_flags() {
+        let config: Config = toml::from_str(
+            r#"
+            [python]
+            runner = "poetry run pytest"
+            flags = ["-x"]
+            env = { DJANGO_SETTINGS_MODULE = "app.settings" }
+            "#,
+        )
+        .unwrap();
+        let mut comm = sample_command();
+        config.apply("python", &mut comm);
+        assert_eq!(
+            comm.to_string(),
+            "DJANGO_SETTINGS_MODULE=app.settings poetry run pytest -x tests/test_stuff.py::TestClass::test_method"
+        );
+    }
+
+    #[test]
+    fn test_apply_cli_runner() {
+        let mut config: Config =
+            toml::from_str("[python]\nrunner = \"poetry run pytest\"").unwrap();
+        config.runner = Some("python -m pytest".to_string());
+        let mut comm = sample_command();
+        config.apply("python", &mut comm);
+        assert_eq!(
+            comm.to_string(),
+            "python -m pytest tests/test_stuff.py::TestClass::test_method"
+        );
+    }
+
+    #[test]
+    fn test_apply_template() {
+        let config: Config = toml::from_str(
+            r#"
+            [python]
+            template = "docker compose run {runner} --rootdir={root} {file}::{namespace}::{test}"
+            "#,
+        )
+        .unwrap();
+        let mut comm = sample_command();
+        config.apply("python", &mut comm);
+        assert_eq!(
+            comm.argv(),
+            vec![
+                "docker",
+                "compose",
+                "run",
+                "pytest",
+                "--rootdir=/project",
+                "tests/test_stuff.py::TestClass::test_method"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_key() {
+        let config: Result<Config, _> = toml::from_str("[python]\nrunnr = \"pytest\"");
+        assert!(config.is_err());
+    }
+}
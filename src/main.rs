@@ -1,25 +1,63 @@
+mod config;
 mod pickers;
+mod state;
 
 use std::process::exit;
+use std::str::FromStr;
 
-use pickers::get_command;
+use config::Config;
+use pickers::{get_command, list_tests};
+use state::State;
 
-use anyhow::Result;
+use anyhow::{anyhow, Error, Result};
 use argh::FromArgs;
 
+enum Format {
+    Text,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            _ => Err(anyhow!("Unknown format {}, use text or json", s)),
+        }
+    }
+}
+
 #[derive(FromArgs)]
 /// Get command to run to run specific test in a file
 struct Opts {
-    // /// specify runner override default runner
-    // #[argh(option, long = "runner")]
-    // runner: Option<String>,
+    /// specify runner override default runner
+    #[argh(option, long = "runner")]
+    runner: Option<String>,
+    /// output format: text (default) or json
+    #[argh(option, long = "format", default = "Format::Text")]
+    format: Format,
+
+    /// list all the tests in the file
+    #[argh(switch, long = "list")]
+    list: bool,
+
+    /// run the command instead of printing it
+    #[argh(switch, long = "run")]
+    run: bool,
+
+    /// rerun the last command from the project of the file
+    #[argh(switch, long = "last")]
+    last: bool,
+
     /// run full test suite
     #[argh(switch, long = "full")]
     full: bool,
 
     // TODO; handle things like `-vv` for increased verbosity
     /// enable verbose flag
-    #[argh(switch, long = "verbose", short='v')]
+    #[argh(switch, long = "verbose", short = 'v')]
     verbose: bool,
 
     /// name of the test file
@@ -31,12 +69,89 @@ struct Opts {
     line_no: Option<usize>,
 }
 
+fn list(args: &Opts, config: &Config) -> Result<()> {
+    let tests = match list_tests(&args.filename, args.verbose, config)? {
+        Some(t) => t,
+        None => exit(1),
+    };
+    match args.format {
+        Format::Text => {
+            for (span, command) in tests {
+                println!(
+                    "{}-{}\t{}\t{}",
+                    span.start,
+                    span.end,
+                    span.name,
+                    command.shell()
+                );
+            }
+        }
+        Format::Json => {
+            let items: Vec<_> = tests
+                .iter()
+                .map(|(span, command)| {
+                    let mut item = command.to_json();
+                    item["name"] = span.name.clone().into();
+                    item["start"] = span.start.into();
+                    item["end"] = span.end.into();
+                    item
+                })
+                .collect();
+            println!("{}", serde_json::Value::from(items));
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let args: Opts = argh::from_env();
 
-    let te = get_command(&args.filename, args.line_no, args.full, args.verbose)?;
+    let mut config = Config::load(&args.filename)?;
+    if args.runner.is_some() {
+        config.runner = args.runner.clone();
+    }
+
+    if args.list {
+        return list(&args, &config);
+    }
+
+    // a broken state file should not get in the way of finding tests
+    let mut state = State::default_path().and_then(|path| match State::load(&path) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            None
+        }
+    });
+
+    let te = if args.last {
+        state.as_ref().and_then(|s| s.last(&args.filename)).cloned()
+    } else {
+        let te = get_command(
+            &args.filename,
+            args.line_no,
+            args.full,
+            args.verbose,
+            &config,
+        )?;
+        if let (Some(s), Some(t)) = (state.as_mut(), te.as_ref()) {
+            if let Err(e) = s.save(t) {
+                eprintln!("Warning: unable to save last command: {:#}", e);
+            }
+        }
+        te
+    };
     if let Some(t) = te {
-        println!("{}", t);
+        if args.run {
+            exit(t.run()?);
+        }
+        match args.format {
+            Format::Text => println!("{}", t.shell()),
+            Format::Json => println!("{}", t.to_json()),
+        }
+    } else if args.last {
+        eprintln!("No previous command found for this project");
+        exit(1);
     } else {
         eprintln!("Unable to find any tests");
         exit(1);
@@ -1,3 +1,5 @@
+use super::{Location, Scope};
+
 use anyhow::Result;
 use regex::Regex;
 use std::fs::File;
@@ -15,6 +17,23 @@ pub struct WithLineNo {
     pub values: Vec<String>,
 }
 
+/// A test or namespace along with the lines it spans
+#[derive(Debug, Clone)]
+pub struct TestSpan {
+    pub scope: Scope,
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// How the end of a block of code is identified
+pub enum Block {
+    /// block ends when the indentation goes back (python)
+    Indent,
+    /// block ends when the braces are balanced (go, rust)
+    Braces,
+}
+
 fn get_exact_line(no: usize, line_no: usize, search_downwards: bool) -> usize {
     if search_downwards {
         line_no + no
@@ -55,10 +74,7 @@ pub fn find_nearest(
     search_downwards: bool,
 ) -> Result<Option<TestCase>> {
     let test = Regex::new(test).unwrap();
-    let namespace = match namespace {
-        Some(ns) => Some(Regex::new(ns).unwrap()),
-        None => None,
-    };
+    let namespace = namespace.map(|ns| Regex::new(ns).unwrap());
     let indent = Regex::new(r"^(\s+).*").unwrap();
     let mut file = File::open(filename).expect("opening file");
     let mut text = String::new();
@@ -70,16 +86,14 @@ pub fn find_nearest(
     }
 
     let mut test_item = None;
-    let mut indent_level = std::usize::MAX;
+    let mut indent_level = usize::MAX;
     for (i, line) in lines.iter().enumerate() {
         if test_item.is_none() {
             let t_caps = test.captures(line);
             if let Some(c) = t_caps {
                 let mut values: Vec<String> = vec![];
-                for v in c.iter() {
-                    if let Some(k) = v {
-                        values.push(k.as_str().to_string())
-                    }
+                for k in c.iter().flatten() {
+                    values.push(k.as_str().to_string())
                 }
                 test_item = Some(TestCase {
                     name: Some(WithLineNo {
@@ -152,8 +166,217 @@ pub fn find_nearest(
     Ok(test_item)
 }
 
+fn get_indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// What the start of a line is in, carried over from the previous
+/// lines so that braces in multi-line strings and comments are skipped
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lexical {
+    Code,
+    Str,
+    /// raw string closed by a quote and the number of `#`s (rust)
+    RawStr(usize),
+    /// backquoted string (go)
+    RawGo,
+    /// block comment with how deeply it is nested
+    Comment(usize),
+}
+
+/// Count of opening minus closing braces in the line, ignoring the
+/// ones in strings, char literals and comments
+pub fn brace_balance(line: &str) -> isize {
+    scan_braces(line, &mut Lexical::Code)
+}
+
+/// Same as `brace_balance`, but starting in the given state and
+/// updating it with where the line ends
+pub fn scan_braces(line: &str, state: &mut Lexical) -> isize {
+    let chars: Vec<char> = line.chars().collect();
+    let next = |i: usize| chars.get(i + 1).copied();
+    let mut balance = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        match *state {
+            Lexical::Str => match chars[i] {
+                '\\' => i += 1,
+                '"' => *state = Lexical::Code,
+                _ => {}
+            },
+            Lexical::RawStr(hashes) => {
+                let closes = chars[i] == '"'
+                    && chars[i + 1..].iter().take_while(|c| **c == '#').count() >= hashes;
+                if closes {
+                    *state = Lexical::Code;
+                    i += hashes;
+                }
+            }
+            Lexical::RawGo => {
+                if chars[i] == '`' {
+                    *state = Lexical::Code;
+                }
+            }
+            Lexical::Comment(depth) => match (chars[i], next(i)) {
+                ('*', Some('/')) => {
+                    *state = match depth {
+                        1 => Lexical::Code,
+                        _ => Lexical::Comment(depth - 1),
+                    };
+                    i += 1;
+                }
+                ('/', Some('*')) => {
+                    *state = Lexical::Comment(depth + 1);
+                    i += 1;
+                }
+                _ => {}
+            },
+            Lexical::Code => match chars[i] {
+                '"' => *state = Lexical::Str,
+                // raw strings, r#"..."# in rust and `...` in go
+                'r' if i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_') => {
+                    let hashes = chars[i + 1..].iter().take_while(|c| **c == '#').count();
+                    if chars.get(i + 1 + hashes) == Some(&'"') {
+                        *state = Lexical::RawStr(hashes);
+                        i += 1 + hashes;
+                    }
+                }
+                '`' => *state = Lexical::RawGo,
+                // char literals, which unlike lifetimes are closed right away
+                '\'' => {
+                    if next(i) == Some('\\') {
+                        i += 3;
+                        while i < chars.len() && chars[i] != '\'' {
+                            i += 1;
+                        }
+                    } else if chars.get(i + 2) == Some(&'\'') {
+                        i += 2;
+                    }
+                }
+                '/' if next(i) == Some('/') => break,
+                '/' if next(i) == Some('*') => {
+                    *state = Lexical::Comment(1);
+                    i += 1;
+                }
+                '{' => balance += 1,
+                '}' => balance -= 1,
+                _ => {}
+            },
+        }
+        i += 1;
+    }
+    balance
+}
+
+/// Blocks opened by lines matching any of the patterns which are still
+/// open at the start of the (1 indexed) line, outermost first. Names
+/// are taken from the first capture group that matched.
+pub fn open_blocks(filename: &str, line_no: usize, patterns: &[&str]) -> Result<Vec<Location>> {
+    let text = std::fs::read_to_string(filename)?;
+    let patterns: Vec<Regex> = patterns.iter().map(|x| Regex::new(x).unwrap()).collect();
+    let mut depth = 0;
+    let mut state = Lexical::Code;
+    let mut chain: Vec<(Location, isize)> = vec![];
+    for (i, line) in text.lines().take(line_no.saturating_sub(1)).enumerate() {
+        let matched = match state {
+            Lexical::Code => patterns.iter().find_map(|re| re.captures(line)),
+            _ => None,
+        };
+        if let Some(c) = matched {
+            if let Some(name) = c.iter().skip(1).flatten().next() {
+                let location = Location {
+                    name: name.as_str().to_string(),
+                    line: i + 1,
+                };
+                chain.push((location, depth));
+            }
+        }
+        depth += scan_braces(line, &mut state);
+        while chain.last().map(|(_, d)| *d >= depth).unwrap_or(false) {
+            chain.pop();
+        }
+    }
+    Ok(chain.into_iter().map(|(l, _)| l).collect())
+}
+
+/// Find the (zero indexed) line where the block starting at `start` ends
+pub fn find_block_end(lines: &[&str], start: usize, block: &Block) -> usize {
+    match block {
+        Block::Indent => {
+            let indent = get_indent(lines[start]);
+            let mut end = start;
+            for (i, line) in lines.iter().enumerate().skip(start + 1) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if get_indent(line) <= indent {
+                    break;
+                }
+                end = i;
+            }
+            end
+        }
+        Block::Braces => {
+            let mut depth = 0;
+            let mut opened = false;
+            let mut state = Lexical::Code;
+            for (i, line) in lines.iter().enumerate().skip(start) {
+                let balance = scan_braces(line, &mut state);
+                if balance != 0 || line.contains('{') {
+                    opened = true;
+                }
+                depth += balance;
+                if opened && depth <= 0 {
+                    return i;
+                }
+            }
+            lines.len().saturating_sub(1)
+        }
+    }
+}
+
+/// Find all the tests and namespaces in the file. Line numbers in the
+/// result are one indexed and the name is the last capture group.
+pub fn find_all(
+    filename: &str,
+    test: &str,
+    namespace: Option<&str>,
+    block: Block,
+) -> Result<Vec<TestSpan>> {
+    let test = Regex::new(test).unwrap();
+    let namespace = namespace.map(|ns| Regex::new(ns).unwrap());
+    let mut file = File::open(filename).expect("opening file");
+    let mut text = String::new();
+    file.read_to_string(&mut text).expect("reading file");
+    let lines: Vec<_> = text.lines().collect();
+
+    let mut spans = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        let (scope, caps) = if let Some(c) = test.captures(line) {
+            (Scope::Test, c)
+        } else if let Some(c) = namespace.as_ref().and_then(|ns| ns.captures(line)) {
+            (Scope::Namespace, c)
+        } else {
+            continue;
+        };
+        let name = caps
+            .iter()
+            .flatten()
+            .last()
+            .map(|x| x.as_str().to_string())
+            .unwrap_or_default();
+        spans.push(TestSpan {
+            scope,
+            name,
+            start: i + 1,
+            end: find_block_end(&lines, i, &block) + 1,
+        });
+    }
+    Ok(spans)
+}
+
 pub fn get_project_root<'a>(filename: &'a str, marker: &'a str) -> &'a str {
-    let mut root = Path::new(filename.clone());
+    let mut root = Path::new(filename);
     // in case we are already at root
     if root.join(marker).exists() {
         return root.to_str().unwrap();
@@ -170,3 +393,67 @@ pub fn get_project_root<'a>(filename: &'a str, marker: &'a str) -> &'a str {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_all_indent() {
+        let spans = find_all(
+            "./fixtures/python/pytest/test_stuff.py",
+            r"^\s*def (test_\w+)",
+            Some(r"^\s*class (\w+)"),
+            Block::Indent,
+        )
+        .unwrap();
+        let spans: Vec<_> = spans
+            .iter()
+            .map(|x| (x.name.as_str(), x.start, x.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("TestClass", 1, 7),
+                ("TestNestedClass", 2, 4),
+                ("test_nestedclass_method", 3, 4),
+                ("test_method", 6, 7),
+                ("TestClassObj", 10, 12),
+                ("test_method_obj", 11, 12),
+                ("test_function", 15, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_brace_balance_literals() {
+        assert_eq!(brace_balance("let x = '{'; // }"), 0);
+        assert_eq!(brace_balance(r"let x = '\''; {"), 1);
+        assert_eq!(brace_balance(r"let x = '\u{7b}';"), 0);
+        assert_eq!(brace_balance(r##"let x = r#"{"}"#; }"##), -1);
+        assert_eq!(brace_balance("fn f<'a>(x: &'a str) {"), 1);
+        assert_eq!(brace_balance("x := `{`"), 0);
+        assert_eq!(brace_balance("/* { */ }"), -1);
+
+        let mut state = Lexical::Code;
+        assert_eq!(scan_braces(r#"const DATA: &str = "{"#, &mut state), 0);
+        assert_eq!(state, Lexical::Str);
+        assert_eq!(scan_braces(r#"}"; {"#, &mut state), 1);
+        assert_eq!(state, Lexical::Code);
+        assert_eq!(scan_braces("/* } /* } */", &mut state), 0);
+        assert_eq!(scan_braces("} */ }", &mut state), -1);
+    }
+
+    #[test]
+    fn test_block_end_braces() {
+        let lines = vec![
+            "func TestX(t *testing.T) {",
+            "    s := \"}\" // }",
+            "    if true {",
+            "    }",
+            "}",
+            "",
+        ];
+        assert_eq!(find_block_end(&lines, 0, &Block::Braces), 4);
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+/// Name of a test or namespace along with the line it is defined on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub name: String,
+    pub line: usize,
+}
+
+/// What the command will end up running
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    #[default]
+    Suite,
+    File,
+    Namespace,
+    Test,
+}
+
+/// Command generated by a picker along with the pieces of information
+/// used to build it so that it can be customized later. Missing fields
+/// are defaulted so that commands saved by older versions still load.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Command {
+    pub language: String,
+    pub framework: String,
+    pub scope: Scope,
+    pub runner: Vec<String>,
+    pub flags: Vec<String>,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// directory to run the command from, defaults to the current one
+    pub cwd: Option<String>,
+    pub root: String,
+    pub file: String,
+    /// namespace of the file itself (eg: module path in rust)
+    pub module: Vec<String>,
+    pub namespace: Vec<Location>,
+    pub test: Option<Location>,
+}
+
+impl Command {
+    pub fn new(runner: &str, root: &str, file: &str) -> Command {
+        Command {
+            language: String::new(),
+            framework: String::new(),
+            scope: Scope::File,
+            runner: runner.split_whitespace().map(|x| x.to_string()).collect(),
+            flags: Vec::new(),
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            cwd: None,
+            root: root.to_string(),
+            file: file.to_string(),
+            module: Vec::new(),
+            namespace: Vec::new(),
+            test: None,
+        }
+    }
+
+    pub fn scope(mut self, scope: Scope) -> Command {
+        self.scope = scope;
+        self
+    }
+
+    pub fn arg(mut self, arg: &str) -> Command {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn verbose(self, verbose: bool) -> Command {
+        if verbose {
+            self.arg("-v")
+        } else {
+            self
+        }
+    }
+
+    pub fn argv(&self) -> Vec<String> {
+        let mut argv = self.runner.clone();
+        argv.extend(self.flags.iter().cloned());
+        argv.extend(self.args.iter().cloned());
+        argv
+    }
+
+    /// Full namespace path of the test, including the file module
+    pub fn namespace_path(&self) -> Vec<String> {
+        let mut path = self.module.clone();
+        path.extend(self.namespace.iter().map(|x| x.name.to_string()));
+        path
+    }
+
+    /// Path of the file relative to the directory the command is run
+    /// from, or the absolute path if it is not within it
+    pub fn file_from_cwd(&self) -> String {
+        let cwd = match &self.cwd {
+            Some(cwd) => cwd,
+            None => return self.file.to_string(),
+        };
+        match Path::new(&self.file).strip_prefix(cwd) {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(_) => fs::canonicalize(&self.file)
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_else(|_| self.file.to_string()),
+        }
+    }
+
+    pub fn working_dir(&self) -> String {
+        match &self.cwd {
+            Some(cwd) => cwd.to_string(),
+            None => env::current_dir()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_else(|_| ".".to_string()),
+        }
+    }
+
+    /// The command as a line for the shell, changing to the directory it
+    /// has to be run from first if that is not the current one
+    pub fn shell(&self) -> String {
+        let same = |cwd: &str| {
+            let canonical = |p: &str| std::fs::canonicalize(p).ok();
+            canonical(cwd).is_some() && canonical(cwd) == canonical(".")
+        };
+        match &self.cwd {
+            Some(cwd) if !same(cwd) => format!("cd {} && {}", quote(cwd), self),
+            _ => self.to_string(),
+        }
+    }
+
+    /// Run the command with output going to the terminal, returns the
+    /// exit code of the process
+    pub fn run(&self) -> Result<i32> {
+        let argv = self.argv();
+        if argv.is_empty() {
+            return Err(anyhow!("Nothing to run"));
+        }
+        let status = process::Command::new(&argv[0])
+            .args(&argv[1..])
+            .envs(&self.env)
+            .current_dir(self.working_dir())
+            .status()
+            .with_context(|| format!("Unable to run {}", argv[0]))?;
+        Ok(status.code().unwrap_or(1))
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "command": self.argv(),
+            "cwd": self.working_dir(),
+            "env": self.env,
+            "language": self.language,
+            "framework": self.framework,
+            "scope": self.scope,
+            "file": self.file,
+            "root": self.root,
+            "module": self.module,
+            "namespace": self.namespace,
+            "test": self.test,
+        })
+    }
+}
+
+/// Quote the argument if the shell would otherwise interpret it
+pub fn quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = self
+            .env
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, quote(v)))
+            .collect();
+        parts.extend(self.argv().iter().map(|x| quote(x)));
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_quotes_args() {
+        let mut comm = Command::new("go test", ".", "main_test.go")
+            .arg("-run")
+            .arg("^TestX$")
+            .arg("./pkg");
+        comm.env
+            .insert("GOFLAGS".to_string(), "-count=1 -v".to_string());
+        assert_eq!(
+            comm.to_string(),
+            "GOFLAGS='-count=1 -v' go test -run '^TestX$' ./pkg"
+        );
+    }
+
+    #[test]
+    fn test_shell_changes_directory() {
+        let mut comm = Command::new("pytest", ".", "test_stuff.py").arg("test_stuff.py");
+        assert_eq!(comm.shell(), "pytest test_stuff.py");
+        comm.cwd = Some(".".to_string());
+        assert_eq!(comm.shell(), "pytest test_stuff.py");
+        comm.cwd = Some("./fixtures/python/pytest".to_string());
+        assert_eq!(
+            comm.shell(),
+            "cd ./fixtures/python/pytest && pytest test_stuff.py"
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut comm = Command::new("pytest", ".", "test_stuff.py")
+            .scope(Scope::Test)
+            .arg("test_stuff.py::TestClass::test_method");
+        comm.cwd = Some("/project".to_string());
+        comm.namespace = vec![Location {
+            name: "TestClass".to_string(),
+            line: 1,
+        }];
+        comm.test = Some(Location {
+            name: "test_method".to_string(),
+            line: 6,
+        });
+        let resp = comm.to_json();
+        assert_eq!(
+            resp["command"],
+            json!(["pytest", "test_stuff.py::TestClass::test_method"])
+        );
+        assert_eq!(resp["cwd"], "/project");
+        assert_eq!(resp["scope"], "test");
+        assert_eq!(resp["namespace"], json!([{"name": "TestClass", "line": 1}]));
+        assert_eq!(resp["test"], json!({"name": "test_method", "line": 6}));
+    }
+
+    #[test]
+    fn test_run_exit_code() {
+        let mut comm = Command::new("sh", ".", "")
+            .arg("-c")
+            .arg("test \"$(pwd)\" = /tmp && exit $CODE");
+        comm.cwd = Some("/tmp".to_string());
+        comm.env.insert("CODE".to_string(), "3".to_string());
+        assert_eq!(comm.run().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_quote_single_quotes() {
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote(""), "''");
+    }
+}
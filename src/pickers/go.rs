@@ -1,53 +1,435 @@
 use super::base;
+#[cfg(feature = "tree-sitter")]
+use super::treesitter;
+use super::{Command, Location, Picker, Scope};
+use crate::config::LanguageConfig;
 
 use anyhow::Result;
+use regex::Regex;
+use std::fs;
 use std::path::Path;
 
-fn find_nearest(filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
-    Ok(base::find_nearest(
-        &filename,
-        r"^\s*func (Test\w+|Example\w+)",
-        None,
-        line_no,
-        false,
-    )?)
+/// Test functions, along with methods of testify suites which have the
+/// suite type captured before the name
+const TEST_REGEX: &str =
+    r"^\s*func (?:\(\s*\w+\s+\*?(\w+)\s*\)\s*)?(Test\w+|Example\w+|Benchmark\w+|Fuzz\w+)";
+/// `t.Run` calls with the name as a string literal
+const SUBTEST_REGEX: &str = r#"\b\w+\.Run\(\s*(?:"((?:[^"\\]|\\.)*)"|`([^`]*)`)\s*,"#;
+
+/// Name of the subtest as reported by `go test`, which replaces
+/// spaces with underscores
+fn subtest_name(literal: &str) -> String {
+    literal
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\")
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
 }
 
-// TODO: make verbose flag configurable
-pub fn get_command(
-    filename: &str,
-    line_no: Option<usize>,
-    full: bool,
-    verbose: bool,
-) -> Result<Option<String>> {
-    let verbose_str = if verbose { " -v" } else { "" };
-    if full {
-        return Ok(Some(format!("go test{} ./...", verbose_str)));
-    }
-    let module_path = match Path::new(&filename).parent().ok_or_else(|| ".") {
-        Ok(m) => m.to_string_lossy().to_string(),
-        _ => ".".to_string(),
+/// `t.Run` calls naming the subtest after a field of the table entry
+/// (`tc.name`) or the key of the map (`name`) being iterated, with the
+/// `*testing.T` of the test as the receiver
+fn table_run_regex(param: &str) -> Regex {
+    Regex::new(&format!(
+        r"\b{}\.Run\(\s*(\w+)(?:\.(\w+))?\s*,",
+        regex::escape(param)
+    ))
+    .unwrap()
+}
+
+/// Name and line of the table driven test case at the line. Only
+/// cases whose name is a string literal can be picked.
+fn find_table_case(filename: &str, parent: usize, line_no: usize) -> Result<Option<Location>> {
+    let text = fs::read_to_string(filename)?;
+    let lines: Vec<&str> = text.lines().collect();
+    if parent == 0 || line_no <= parent || line_no > lines.len() {
+        return Ok(None);
+    }
+    let param = Regex::new(r"\(\s*(\w+)\s+\*testing\.T\b").unwrap();
+    let runner = match param.captures(lines[parent - 1]) {
+        Some(c) => table_run_regex(&c[1]),
+        None => return Ok(None),
     };
-    match line_no {
-        Some(ln) => {
-            let mut test_case = find_nearest(&filename, ln)?;
-            if let Some(t) = test_case.as_mut() {
-                let mut namespace_path = format!("");
-                if let Some(tn) = t.name.as_mut() {
-                    namespace_path = format!("{}", tn.values[tn.values.len() - 1].to_string());
-                }
-                let comm = format!(
-                    "go test{} -run '^{}$' {}",
-                    verbose_str, namespace_path, module_path
-                );
-                return Ok(Some(comm));
-            };
-            Ok(None)
+    let end = base::find_block_end(&lines, parent - 1, &base::Block::Braces);
+    let (run_line, caps) =
+        match (parent..=end).find_map(|i| runner.captures(lines[i]).map(|c| (i, c))) {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+    // cases are defined before they are run
+    let current = line_no - 1;
+    if current >= run_line {
+        return Ok(None);
+    }
+
+    let literal = r#""((?:[^"\\]|\\.)*)""#;
+    let entry = match caps.get(2) {
+        Some(field) => Regex::new(&format!(r"\b{}\s*:\s*{}", field.as_str(), literal)),
+        None => Regex::new(&format!(r"^\s*{}\s*:", literal)),
+    }
+    .unwrap();
+    // brace depth at the start of each line
+    let mut depth = vec![0; run_line + 1];
+    for i in parent..run_line {
+        depth[i + 1] = depth[i] + base::brace_balance(lines[i]);
+    }
+
+    // the closest entry above is the one the line is in as long as the
+    // table has not been closed in between
+    let above = (parent..=current)
+        .rev()
+        .find_map(|i| entry.captures(lines[i]).map(|c| (i, c)));
+    if let Some((i, c)) = above {
+        let at = c.get(0).map(|m| m.start()).unwrap_or(0);
+        let level = depth[i] + base::brace_balance(&lines[i][..at]);
+        // struct fields are within the braces of the entry, map keys
+        // are at the level of the table
+        let table = if caps.get(2).is_some() {
+            level - 1
+        } else {
+            level
+        };
+        if i == current || (i + 1..=current).all(|j| depth[j] > table) {
+            return Ok(Some(Location {
+                name: c[1].to_string(),
+                line: i + 1,
+            }));
+        }
+    }
+
+    // lines opening an entry which has its name further down
+    if base::brace_balance(lines[current]) > 0 {
+        for (i, line) in lines.iter().enumerate().take(run_line).skip(current + 1) {
+            if line.trim_start().starts_with('}') {
+                break;
+            }
+            if let Some(c) = entry.captures(line) {
+                return Ok(Some(Location {
+                    name: c[1].to_string(),
+                    line: i + 1,
+                }));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Find the test function which runs the testify suite using
+/// `suite.Run`, looking in the file first and then the rest of the
+/// test files in the package
+fn find_suite_runner(filename: &str, suite: &str) -> Result<Option<Location>> {
+    let runner = Regex::new(&format!(
+        r"\bsuite\.Run\(\s*\w+\s*,\s*(?:new\(\s*{0}\s*\)|&{0}\s*\{{)",
+        suite
+    ))
+    .unwrap();
+    let func = Regex::new(r"^\s*func (Test\w+)").unwrap();
+    let path = Path::new(filename);
+    let mut files = vec![path.to_path_buf()];
+    if let Ok(entries) = fs::read_dir(
+        path.parent()
+            .filter(|x| !x.as_os_str().is_empty())
+            .unwrap_or(Path::new(".")),
+    ) {
+        let mut others: Vec<_> = entries
+            .flatten()
+            .map(|x| x.path())
+            .filter(|x| {
+                x.to_string_lossy().ends_with("_test.go") && x.file_name() != path.file_name()
+            })
+            .collect();
+        others.sort();
+        files.extend(others);
+    }
+
+    for file in files {
+        let text = match fs::read_to_string(&file) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        let mut current = None;
+        for (i, line) in text.lines().enumerate() {
+            if let Some(c) = func.captures(line) {
+                current = Some(Location {
+                    name: c[1].to_string(),
+                    line: i + 1,
+                });
+            }
+            if runner.is_match(line) && current.is_some() {
+                return Ok(current);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Pattern for `-run` matching exactly the test and subtests given.
+/// Each level of subtests is a separate regex separated by a `/`.
+fn run_pattern(path: &[String]) -> String {
+    path.iter()
+        .map(|x| format!("^{}$", regex::escape(x)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub struct GoTest {
+    /// value for `-fuzztime`, fuzzing runs until stopped without it
+    fuzz_time: Option<String>,
+}
+
+impl GoTest {
+    pub fn new(config: &LanguageConfig) -> GoTest {
+        GoTest {
+            fuzz_time: config.fuzz_time.clone(),
+        }
+    }
+
+    /// Package of the file relative to the module root if there is a
+    /// `go.mod`, or the directory of the file otherwise
+    fn module_path(&self, filename: &str) -> String {
+        let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+        let relative = match module_root(filename).map(|root| dir.strip_prefix(root)) {
+            Some(Ok(r)) => r.to_string_lossy().to_string(),
+            // files in the current directory have an empty parent
+            _ if dir.as_os_str().is_empty() => return ".".to_string(),
+            _ => return dir.to_string_lossy().to_string(),
+        };
+        if relative.is_empty() {
+            ".".to_string()
+        } else {
+            format!("./{}", relative)
+        }
+    }
+}
+
+/// Directory with the `go.mod` of the module the file is a part of
+fn module_root(filename: &str) -> Option<&str> {
+    let root = base::get_project_root(filename, "go.mod");
+    Some(root).filter(|r| Path::new(r).join("go.mod").is_file())
+}
+
+/// Commands are run from the module root as packages are relative to it
+fn new_command(filename: &str) -> Command {
+    let root = base::get_project_root(filename, "go.mod");
+    let mut command = Command::new("go test", root, filename);
+    // bare filenames are already in the module root
+    command.cwd = module_root(filename)
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_string());
+    command
+}
+
+/// Values that can be used in build constraints but are set by the
+/// toolchain and so should not be passed in using `-tags`
+const BUILTIN_TAGS: &[&str] = &[
+    "aix",
+    "android",
+    "darwin",
+    "dragonfly",
+    "freebsd",
+    "illumos",
+    "ios",
+    "js",
+    "linux",
+    "netbsd",
+    "openbsd",
+    "plan9",
+    "solaris",
+    "wasip1",
+    "windows",
+    "unix",
+    "386",
+    "amd64",
+    "arm",
+    "arm64",
+    "loong64",
+    "mips",
+    "mipsle",
+    "mips64",
+    "mips64le",
+    "ppc64",
+    "ppc64le",
+    "riscv64",
+    "s390x",
+    "wasm",
+    "cgo",
+    "gc",
+    "gccgo",
+];
+
+/// `-tags` needed to satisfy the build constraints at the top of the
+/// file, negated tags are left out
+fn build_tags(filename: &str) -> Result<Vec<String>> {
+    let text = fs::read_to_string(filename)?;
+    let tag = Regex::new(r"(!?)([\w.]+)").unwrap();
+    let mut tags: Vec<String> = vec![];
+    // constraints can only appear before the package clause
+    for line in text.lines().take_while(|l| !l.starts_with("package ")) {
+        let expr = match line.strip_prefix("//go:build ") {
+            Some(e) => e,
+            None => continue,
+        };
+        for c in tag.captures_iter(expr) {
+            let name = c[2].to_string();
+            let builtin = BUILTIN_TAGS.contains(&name.as_str()) || name.starts_with("go1.");
+            if c[1].is_empty() && !builtin && !tags.contains(&name) {
+                tags.push(name);
+            }
         }
-        None => {
-            let comm = format!("go test -v {}", module_path);
-            return Ok(Some(comm));
+    }
+    if tags.is_empty() {
+        return Ok(vec![]);
+    }
+    Ok(vec!["-tags".to_string(), tags.join(",")])
+}
+
+impl Default for GoTest {
+    fn default() -> GoTest {
+        GoTest::new(&LanguageConfig::default())
+    }
+}
+
+// TODO: make verbose flag configurable
+impl Picker for GoTest {
+    fn language(&self) -> &'static str {
+        "go"
+    }
+
+    fn framework(&self) -> &'static str {
+        "gotest"
+    }
+
+    fn detect(&self, filename: &str) -> bool {
+        filename.ends_with(".go")
+    }
+
+    fn find_nearest(&self, filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
+        #[cfg(feature = "tree-sitter")]
+        if let Ok(t) = treesitter::find_nearest(filename, &treesitter::Lang::Go, line_no) {
+            return Ok(t);
         }
+        base::find_nearest(filename, TEST_REGEX, None, line_no, false)
+    }
+
+    fn find_all(&self, filename: &str) -> Result<Vec<base::TestSpan>> {
+        base::find_all(filename, TEST_REGEX, None, base::Block::Braces)
+    }
+
+    fn suite_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        Ok(Some(
+            new_command(filename)
+                .scope(Scope::Suite)
+                .verbose(verbose)
+                .arg("./..."),
+        ))
+    }
+
+    fn file_command(&self, filename: &str, _verbose: bool) -> Result<Option<Command>> {
+        let mut command = new_command(filename).verbose(true);
+        command.args.extend(build_tags(filename)?);
+        Ok(Some(command.arg(&self.module_path(filename))))
+    }
+
+    fn nearest_command(
+        &self,
+        filename: &str,
+        line_no: usize,
+        verbose: bool,
+    ) -> Result<Option<Command>> {
+        let test_case = self.find_nearest(filename, line_no)?;
+        if let Some(t) = test_case {
+            let mut command = new_command(filename).scope(Scope::Test);
+            let suite = t
+                .name
+                .as_ref()
+                .filter(|tn| tn.values.len() > 2)
+                .map(|tn| tn.values[1].to_string());
+            command.test = t.name.map(|tn| Location {
+                name: tn.values[tn.values.len() - 1].to_string(),
+                line: tn.no,
+            });
+
+            // methods of testify suites are selected using -testify.m
+            // after selecting the test function running the suite
+            if let Some(suite) = suite {
+                let method = command
+                    .test
+                    .as_ref()
+                    .map(|x| x.name.to_string())
+                    .unwrap_or_default();
+                command
+                    .namespace
+                    .extend(find_suite_runner(filename, &suite)?);
+                command = command.verbose(verbose);
+                command.args.extend(build_tags(filename)?);
+                if let Some(runner) = command.namespace.first() {
+                    let pattern = run_pattern(&[runner.name.to_string()]);
+                    command = command.arg("-run").arg(&pattern);
+                }
+                // flags go test does not know end the package list, so
+                // the package has to come before -testify.m
+                return Ok(Some(
+                    command
+                        .arg(&self.module_path(filename))
+                        .arg("-testify.m")
+                        .arg(&run_pattern(&[method])),
+                ));
+            }
+
+            // subtests within the test that enclose the line
+            if let Some(parent) = command.test.take() {
+                let subtests: Vec<Location> =
+                    base::open_blocks(filename, line_no + 1, &[SUBTEST_REGEX])?
+                        .into_iter()
+                        .filter(|x| x.line > parent.line)
+                        .map(|x| Location {
+                            name: subtest_name(&x.name),
+                            line: x.line,
+                        })
+                        .collect();
+                let case = if subtests.is_empty() {
+                    find_table_case(filename, parent.line, line_no)?.map(|x| Location {
+                        name: subtest_name(&x.name),
+                        line: x.line,
+                    })
+                } else {
+                    None
+                };
+                command.namespace.push(parent);
+                command.namespace.extend(subtests);
+                command.namespace.extend(case);
+                command.test = command.namespace.pop();
+            }
+            let mut path = command.namespace_path();
+            path.extend(command.test.iter().map(|x| x.name.to_string()));
+            let mut command = command.verbose(verbose);
+            command.args.extend(build_tags(filename)?);
+            // benchmarks and fuzz targets are not run by -run, which
+            // is set to match nothing so that tests are skipped
+            let name = path.first().map(|x| x.to_string()).unwrap_or_default();
+            if name.starts_with("Benchmark") {
+                command = command
+                    .arg("-run")
+                    .arg("^$")
+                    .arg("-bench")
+                    .arg(&run_pattern(&path))
+                    .arg("-benchmem");
+            } else if name.starts_with("Fuzz") {
+                command = command
+                    .arg("-run")
+                    .arg("^$")
+                    .arg("-fuzz")
+                    .arg(&run_pattern(&[name]));
+                if let Some(fuzz_time) = &self.fuzz_time {
+                    command = command.arg("-fuzztime").arg(fuzz_time);
+                }
+            } else {
+                command = command.arg("-run").arg(&run_pattern(&path));
+            }
+            return Ok(Some(command.arg(&self.module_path(filename))));
+        };
+        Ok(None)
     }
 }
 
@@ -57,7 +439,8 @@ mod tests {
 
     #[test]
     fn test_go_simple_thingy() {
-        let resp = find_nearest("./fixtures/go/gotest/main_test.go", 21)
+        let resp = GoTest::default()
+            .find_nearest("./fixtures/go/gotest/main_test.go", 21)
             .unwrap()
             .unwrap();
 
@@ -69,17 +452,29 @@ mod tests {
         assert_eq!(resp.namespace.len(), 0);
     }
 
+    #[test]
+    fn test_go_find_all() {
+        let resp = GoTest::default()
+            .find_all("./fixtures/go/gotest/main_test.go")
+            .unwrap();
+        assert_eq!(resp.len(), 1);
+        assert_eq!(resp[0].name, "TestInputParseBasic");
+        assert_eq!((resp[0].start, resp[0].end), (8, 28));
+    }
+
     #[test]
     fn test_go_file_command() {
-        let resp = get_command("./fixtures/go/gotest/main_test.go", None, false, false)
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/main_test.go", None, false, false)
             .unwrap()
             .unwrap();
-        assert_eq!(resp, "go test -v ./fixtures/go/gotest");
+        assert_eq!(resp.to_string(), "go test -v ./fixtures/go/gotest");
     }
 
     #[test]
     fn test_go_simple_on_func() {
-        let resp = find_nearest("./fixtures/go/gotest/main_test.go", 8)
+        let resp = GoTest::default()
+            .find_nearest("./fixtures/go/gotest/main_test.go", 8)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 8);
@@ -92,39 +487,207 @@ mod tests {
 
     #[test]
     fn test_go_simple_command_normal() {
-        let resp = get_command("./fixtures/go/gotest/main_test.go", Some(21), false, false)
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/main_test.go", Some(21), false, false)
             .unwrap()
             .unwrap();
         assert_eq!(
-            resp,
+            resp.to_string(),
             "go test -run '^TestInputParseBasic$' ./fixtures/go/gotest"
         );
     }
 
+    #[test]
+    fn test_go_subtests() {
+        let command = |ln| {
+            GoTest::default()
+                .get_command(
+                    "./fixtures/go/gotest/subtest_test.go",
+                    Some(ln),
+                    false,
+                    false,
+                )
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(
+            command(7).to_string(),
+            "go test -run '^TestSubtests$/^simple_case$' ./fixtures/go/gotest"
+        );
+        assert_eq!(
+            command(14).to_string(),
+            "go test -run '^TestSubtests$/^with_\\(parens\\)\\+$/^nested_one$' ./fixtures/go/gotest"
+        );
+        assert_eq!(command(14).namespace.len(), 2);
+        assert_eq!(
+            command(11).to_string(),
+            "go test -run '^TestSubtests$' ./fixtures/go/gotest"
+        );
+    }
+
+    #[test]
+    fn test_go_table_cases() {
+        let command = |ln| {
+            GoTest::default()
+                .get_command("./fixtures/go/gotest/table_test.go", Some(ln), false, false)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        let pkg = "./fixtures/go/gotest";
+        assert_eq!(
+            command(10),
+            format!("go test -run '^TestTable$/^empty_input$' {}", pkg)
+        );
+        assert_eq!(
+            command(11),
+            format!("go test -run '^TestTable$/^with_spaces$' {}", pkg)
+        );
+        assert_eq!(
+            command(14),
+            format!("go test -run '^TestTable$/^with_spaces$' {}", pkg)
+        );
+        assert_eq!(
+            command(29),
+            format!("go test -run '^TestMap$/^upper_case$' {}", pkg)
+        );
+        // not on a case or within the subtest itself
+        assert_eq!(command(7), format!("go test -run '^TestTable$' {}", pkg));
+        assert_eq!(command(19), format!("go test -run '^TestTable$' {}", pkg));
+        // closing braces of the table
+        assert_eq!(command(15), format!("go test -run '^TestTable$' {}", pkg));
+        assert_eq!(command(30), format!("go test -run '^TestMap$' {}", pkg));
+    }
+
+    #[test]
+    fn test_go_testify_suite() {
+        let command = |ln| {
+            GoTest::default()
+                .get_command("./fixtures/go/gotest/suite_test.go", Some(ln), false, false)
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(
+            command(14).to_string(),
+            "go test -run '^TestMySuite$' ./fixtures/go/gotest -testify.m '^TestFoo$'"
+        );
+        assert_eq!(command(14).namespace[0].line, 21);
+        // suite run from another file in the package
+        assert_eq!(
+            command(17).to_string(),
+            "go test -run '^TestOtherSuite$' ./fixtures/go/gotest -testify.m '^TestBar$'"
+        );
+        assert_eq!(
+            command(21).to_string(),
+            "go test -run '^TestMySuite$' ./fixtures/go/gotest"
+        );
+    }
+
+    #[test]
+    fn test_go_benchmark_and_fuzz() {
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/bench_test.go", Some(7), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "go test -run '^$' -bench '^BenchmarkParse$' -benchmem ./fixtures/go/gotest"
+        );
+
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/bench_test.go", Some(14), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "go test -run '^$' -fuzz '^FuzzParse$' ./fixtures/go/gotest"
+        );
+
+        let config = LanguageConfig {
+            fuzz_time: Some("30s".to_string()),
+            ..LanguageConfig::default()
+        };
+        let resp = GoTest::new(&config)
+            .get_command("./fixtures/go/gotest/bench_test.go", Some(14), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "go test -run '^$' -fuzz '^FuzzParse$' -fuzztime 30s ./fixtures/go/gotest"
+        );
+    }
+
+    #[test]
+    fn test_go_module_relative_package() {
+        let filename = "./fixtures/go/module/pkg/parser/parser_test.go";
+        let resp = GoTest::default()
+            .get_command(filename, Some(8), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "go test -tags integration,postgres -run '^TestParse$' ./pkg/parser"
+        );
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/go/module"));
+
+        let resp = GoTest::default()
+            .get_command(filename, None, false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "go test -v -tags integration,postgres ./pkg/parser"
+        );
+
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/module/root_test.go", None, true, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "go test ./...");
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/go/module"));
+    }
+
+    #[test]
+    fn test_go_module_root_package() {
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/module/root_test.go", Some(5), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "go test -run '^TestRoot$' .");
+    }
+
+    #[test]
+    fn test_go_subtest_name() {
+        assert_eq!(subtest_name(r#"say \"hi\" there"#), "say_\"hi\"_there");
+    }
+
     #[test]
     fn test_go_full_command_normal() {
-        let resp = get_command("./fixtures/go/gotest/main_test.go", None, true, false)
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/main_test.go", None, true, false)
             .unwrap()
             .unwrap();
-        assert_eq!(resp, "go test ./...");
+        assert_eq!(resp.to_string(), "go test ./...");
     }
 
     #[test]
     fn test_go_simple_command_verbose() {
-        let resp = get_command("./fixtures/go/gotest/main_test.go", Some(21), false, true)
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/main_test.go", Some(21), false, true)
             .unwrap()
             .unwrap();
         assert_eq!(
-            resp,
+            resp.to_string(),
             "go test -v -run '^TestInputParseBasic$' ./fixtures/go/gotest"
         );
     }
 
     #[test]
     fn test_go_full_command_verbose() {
-        let resp = get_command("./fixtures/go/gotest/main_test.go", None, true, true)
+        let resp = GoTest::default()
+            .get_command("./fixtures/go/gotest/main_test.go", None, true, true)
             .unwrap()
             .unwrap();
-        assert_eq!(resp, "go test -v ./...");
+        assert_eq!(resp.to_string(), "go test -v ./...");
     }
 }
@@ -1,27 +1,169 @@
 mod base;
+mod command;
 mod go;
 mod python;
 mod rust;
+#[cfg(feature = "tree-sitter")]
+mod treesitter;
+use crate::config::Config;
 use anyhow::Result;
 
+pub use base::TestSpan;
+pub use command::{Command, Location, Scope};
+pub use python::Environment;
+pub use rust::Runner as CargoRunner;
+
+/// A picker knows how to find tests in files of a specific
+/// language/framework and build the commands needed to run them.
+pub trait Picker {
+    /// Name of the language, used to look up config overrides
+    fn language(&self) -> &'static str;
+
+    /// Name of the test framework the commands are generated for
+    fn framework(&self) -> &'static str;
+
+    /// Check if the picker can handle the given file
+    fn detect(&self, filename: &str) -> bool;
+
+    /// Find the test (and its namespaces) nearest to `line_no`
+    fn find_nearest(&self, filename: &str, line_no: usize) -> Result<Option<base::TestCase>>;
+
+    /// Find all the tests and namespaces in the file
+    fn find_all(&self, _filename: &str) -> Result<Vec<base::TestSpan>> {
+        Ok(vec![])
+    }
+
+    /// Command to run the full test suite
+    fn suite_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>>;
+
+    /// Command to run all the tests in the file
+    fn file_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>>;
+
+    /// Command to run the test nearest to `line_no`
+    fn nearest_command(
+        &self,
+        filename: &str,
+        line_no: usize,
+        verbose: bool,
+    ) -> Result<Option<Command>>;
+
+    fn get_command(
+        &self,
+        filename: &str,
+        line_no: Option<usize>,
+        full: bool,
+        verbose: bool,
+    ) -> Result<Option<Command>> {
+        if full {
+            return self.suite_command(filename, verbose);
+        }
+        match line_no {
+            Some(ln) => self.nearest_command(filename, ln, verbose),
+            None => self.file_command(filename, verbose),
+        }
+    }
+}
+
+/// List of available pickers. When multiple pickers can handle a
+/// file, the one registered first wins.
+pub struct Registry {
+    pickers: Vec<Box<dyn Picker>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry {
+            pickers: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, picker: Box<dyn Picker>) {
+        self.pickers.push(picker);
+    }
+
+    pub fn pick(&self, filename: &str) -> Option<&dyn Picker> {
+        self.pickers
+            .iter()
+            .find(|p| p.detect(filename))
+            .map(|p| p.as_ref())
+    }
+}
+
+impl Registry {
+    /// Registry with all the builtin pickers set up using the config
+    pub fn from_config(config: &Config) -> Registry {
+        let mut registry = Registry::new();
+        let python = config.language("python");
+        registry.register(Box::new(python::Unittest::django(&python)));
+        registry.register(Box::new(python::Unittest::nose2(&python)));
+        registry.register(Box::new(python::Unittest::new(&python)));
+        registry.register(Box::new(python::Pytest::new(&python)));
+        registry.register(Box::new(rust::CargoTest::nextest(&config.language("rust"))));
+        registry.register(Box::new(rust::CargoTest::new(&config.language("rust"))));
+        registry.register(Box::new(go::GoTest::new(&config.language("go"))));
+        registry
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::from_config(&Config::default())
+    }
+}
+
 pub fn get_command(
     filename: &str,
     line_no: Option<usize>,
     full: bool,
     verbose: bool,
-) -> Result<Option<String>> {
-    if filename.ends_with(".py") {
-        python::get_command(filename, line_no, full, verbose)
-    } else if filename.ends_with(".rs") {
-        rust::get_command(filename, line_no, full, verbose)
-    } else if filename.ends_with(".go") {
-        go::get_command(filename, line_no, full, verbose)
-    } else {
-        eprintln!("Error: Unknown filetype for file {}", filename);
-        Ok(None)
+    config: &Config,
+) -> Result<Option<Command>> {
+    match Registry::from_config(config).pick(filename) {
+        Some(picker) => {
+            let mut command = picker.get_command(filename, line_no, full, verbose)?;
+            if let Some(c) = command.as_mut() {
+                finalize(picker, config, c);
+            }
+            Ok(command)
+        }
+        None => {
+            eprintln!("Error: Unknown filetype for file {}", filename);
+            Ok(None)
+        }
     }
 }
 
+/// List all the tests in the file along with the command to run each
+pub fn list_tests(
+    filename: &str,
+    verbose: bool,
+    config: &Config,
+) -> Result<Option<Vec<(TestSpan, Command)>>> {
+    let registry = Registry::from_config(config);
+    let picker = match registry.pick(filename) {
+        Some(picker) => picker,
+        None => {
+            eprintln!("Error: Unknown filetype for file {}", filename);
+            return Ok(None);
+        }
+    };
+
+    let mut tests = vec![];
+    for span in picker.find_all(filename)? {
+        if let Some(mut c) = picker.nearest_command(filename, span.start, verbose)? {
+            finalize(picker, config, &mut c);
+            tests.push((span, c));
+        }
+    }
+    Ok(Some(tests))
+}
+
+fn finalize(picker: &dyn Picker, config: &Config, command: &mut Command) {
+    command.language = picker.language().to_string();
+    command.framework = picker.framework().to_string();
+    config.apply(picker.language(), command);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,17 +175,63 @@ mod tests {
             Some(16),
             false,
             false,
+            &Config::default(),
         )
         .unwrap();
-        assert_eq!(resp, None);
+        assert!(resp.is_none());
     }
 
     #[test]
-    fn test_python_no_lineno() {
-        let resp = get_command("./fixtures/python/pytest/test_stuff.py", None, false, false)
+    fn test_registry_first_match_wins() {
+        struct Custom;
+        impl Picker for Custom {
+            fn language(&self) -> &'static str {
+                "python"
+            }
+            fn framework(&self) -> &'static str {
+                "custom"
+            }
+            fn detect(&self, filename: &str) -> bool {
+                filename.ends_with(".py")
+            }
+            fn find_nearest(&self, _: &str, _: usize) -> Result<Option<base::TestCase>> {
+                Ok(None)
+            }
+            fn suite_command(&self, _: &str, _: bool) -> Result<Option<Command>> {
+                Ok(Some(Command::new("custom", ".", "")))
+            }
+            fn file_command(&self, _: &str, _: bool) -> Result<Option<Command>> {
+                Ok(Some(Command::new("custom", ".", "")))
+            }
+            fn nearest_command(&self, _: &str, _: usize, _: bool) -> Result<Option<Command>> {
+                Ok(Some(Command::new("custom", ".", "")))
+            }
+        }
+
+        let mut registry = Registry::new();
+        registry.register(Box::new(Custom));
+        registry.register(Box::new(python::Pytest::default()));
+        let resp = registry
+            .pick("./fixtures/python/pytest/test_stuff.py")
             .unwrap()
+            .get_command("./fixtures/python/pytest/test_stuff.py", None, false, false)
             .unwrap();
-        assert_eq!(resp, "pytest ./fixtures/python/pytest/test_stuff.py");
+        assert_eq!(resp.unwrap().to_string(), "custom");
+        assert!(registry.pick("./fixtures/go/gotest/main_test.go").is_none());
+    }
+
+    #[test]
+    fn test_python_no_lineno() {
+        let resp = get_command(
+            "./fixtures/python/pytest/test_stuff.py",
+            None,
+            false,
+            false,
+            &Config::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.to_string(), "pytest test_stuff.py");
     }
 
     #[test]
@@ -53,13 +241,11 @@ mod tests {
             Some(16),
             false,
             false,
+            &Config::default(),
         )
         .unwrap()
         .unwrap();
-        assert_eq!(
-            resp,
-            "pytest ./fixtures/python/pytest/test_stuff.py::test_function"
-        );
+        assert_eq!(resp.to_string(), "pytest test_stuff.py::test_function");
     }
 
     #[test]
@@ -69,9 +255,130 @@ mod tests {
             Some(4),
             false,
             false,
+            &Config::default(),
         )
         .unwrap()
         .unwrap();
-        assert_eq!(resp, "pytest ./fixtures/python/pytest/test_stuff.py::TestClass::TestNestedClass::test_nestedclass_method");
+        assert_eq!(
+            resp.to_string(),
+            "pytest test_stuff.py::TestClass::TestNestedClass::test_nestedclass_method"
+        );
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let filename = "./fixtures/config/test_config.py";
+        let config = Config::load(filename).unwrap();
+        let resp = get_command(filename, Some(2), false, false, &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "poetry run pytest -x test_config.py::test_config"
+        );
+
+        let filename = "./fixtures/config/main_test.go";
+        let config = Config::load(filename).unwrap();
+        let resp = get_command(filename, Some(6), false, false, &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "CGO_ENABLED=1 go test -race -run '^TestConfig$' ./fixtures/config"
+        );
+    }
+
+    #[test]
+    fn test_template_file_relative_to_cwd() {
+        let config: Config =
+            toml::from_str("[python]\ntemplate = \"{runner} {file}::{test}\"").unwrap();
+        let resp = get_command(
+            "./fixtures/python/rootdir/tests/unit/test_root.py",
+            Some(2),
+            false,
+            false,
+            &config,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/python/rootdir"));
+        assert_eq!(
+            resp.to_string(),
+            "pytest tests/unit/test_root.py::test_root"
+        );
+    }
+
+    #[test]
+    fn test_go_template_file_relative_to_module() {
+        let config: Config = toml::from_str("[go]\ntemplate = \"{runner} {file}\"").unwrap();
+        let resp = get_command(
+            "./fixtures/go/module/root_test.go",
+            Some(5),
+            false,
+            false,
+            &config,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/go/module"));
+        assert_eq!(resp.to_string(), "go test root_test.go");
+    }
+
+    #[test]
+    fn test_rust_framework_from_config() {
+        let config: Config = toml::from_str("[rust]\nframework = \"nextest\"").unwrap();
+        let resp = get_command(
+            "./fixtures/rust/cargo/src/pickers/tester.rs",
+            Some(5),
+            false,
+            false,
+            &config,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.framework, "nextest");
+        assert_eq!(resp.runner, vec!["cargo", "nextest", "run"]);
+    }
+
+    #[test]
+    fn test_python_framework_detected() {
+        let resp = get_command(
+            "./fixtures/python/django/app/tests.py",
+            Some(5),
+            false,
+            false,
+            &Config::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.framework, "django");
+        assert_eq!(
+            resp.to_string(),
+            "./manage.py test app.tests.TestViews.test_index"
+        );
+    }
+
+    #[test]
+    fn test_list_tests() {
+        let resp = list_tests(
+            "./fixtures/python/pytest/test_stuff.py",
+            false,
+            &Config::default(),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.len(), 8);
+        assert_eq!(resp[1].0.name, "TestNestedClass");
+        assert_eq!(resp[1].0.scope, Scope::Namespace);
+        assert_eq!(
+            resp[1].1.to_string(),
+            "pytest test_stuff.py::TestClass::TestNestedClass"
+        );
+        assert_eq!(resp[7].0.name, "test_async_function");
+        assert_eq!((resp[7].0.start, resp[7].0.end), (19, 20));
+        assert_eq!(
+            resp[7].1.to_string(),
+            "pytest test_stuff.py::test_async_function"
+        );
     }
 }
@@ -1,59 +1,1026 @@
 use super::base;
+#[cfg(feature = "tree-sitter")]
+use super::treesitter;
+use super::{Command, Location, Picker, Scope};
+use crate::config::LanguageConfig;
 
 use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-fn find_nearest(filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
-    Ok(base::find_nearest(
-        &filename,
-        r"^\s*(async )?def (test_\w+)",
-        Some(r"^\s*class (\w+) ?.*:"),
+/// Patterns the names of test functions and classes have to match
+#[derive(Debug, Clone)]
+struct Names {
+    functions: String,
+    classes: String,
+}
+
+impl Default for Names {
+    fn default() -> Names {
+        Names {
+            functions: r"test_\w+".to_string(),
+            classes: r"\w+".to_string(),
+        }
+    }
+}
+
+impl Names {
+    /// Names from `python_functions` and `python_classes` in the
+    /// pytest config of the project
+    fn from_config(filename: &str) -> Names {
+        let mut names = Names::default();
+        if let Some(f) = pytest_option(filename, "python_functions") {
+            names.functions = glob_regex(&f);
+        }
+        if let Some(c) = pytest_option(filename, "python_classes") {
+            names.classes = glob_regex(&c);
+        }
+        names
+    }
+
+    fn test_regex(&self) -> String {
+        format!(r"^\s*(async )?def ({})\b", self.functions)
+    }
+
+    fn namespace_regex(&self) -> String {
+        format!(r"^\s*class ({})\b ?.*:", self.classes)
+    }
+}
+
+/// Regex for pytest name patterns, which are globs or prefixes
+fn glob_regex(patterns: &[String]) -> String {
+    let patterns: Vec<String> = patterns
+        .iter()
+        .map(|p| {
+            if !p.contains(['*', '?']) {
+                return format!(r"{}\w*", regex::escape(p));
+            }
+            p.chars()
+                .map(|c| match c {
+                    '*' => r"\w*".to_string(),
+                    '?' => r"\w".to_string(),
+                    _ => regex::escape(&c.to_string()),
+                })
+                .collect()
+        })
+        .collect();
+    format!("(?:{})", patterns.join("|"))
+}
+
+/// Line of the function if the line is within the decorators above it
+fn decorated_def(filename: &str, line_no: usize) -> Result<Option<usize>> {
+    let text = fs::read_to_string(filename)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let def = Regex::new(r"^\s*(async\s+)?(def|class)\b").unwrap();
+    if line_no == 0 || line_no > lines.len() {
+        return Ok(None);
+    }
+    let current = line_no - 1;
+    let start = (0..=current)
+        .rev()
+        .take_while(|i| *i == current || !def.is_match(lines[*i]))
+        .find(|i| lines[*i].trim_start().starts_with('@'));
+    let mut i = match start {
+        Some(s) if !def.is_match(lines[current]) => s,
+        _ => return Ok(None),
+    };
+
+    // skip over the decorators, which could span multiple lines
+    let mut depth = 0;
+    while i < lines.len() {
+        let line = lines[i].trim_start();
+        if depth == 0 && def.is_match(lines[i]) {
+            return Ok(Some(i + 1));
+        }
+        if depth == 0 && !line.is_empty() && !line.starts_with(['@', '#']) {
+            return Ok(None);
+        }
+        for (_, c, _) in structure(line) {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = (depth - 1).max(0),
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    Ok(None)
+}
+
+fn find_nearest(filename: &str, line_no: usize, names: &Names) -> Result<Option<base::TestCase>> {
+    let line_no = decorated_def(filename, line_no)?.unwrap_or(line_no);
+    #[cfg(feature = "tree-sitter")]
+    if let Ok(t) = treesitter::find_nearest(
+        filename,
+        &treesitter::Lang::Python(names.functions.clone(), names.classes.clone()),
+        line_no,
+    ) {
+        return Ok(t);
+    }
+    base::find_nearest(
+        filename,
+        &names.test_regex(),
+        Some(&names.namespace_regex()),
         line_no,
         false,
-    )?)
-}
-
-pub fn get_command(
-    filename: &str,
-    line_no: Option<usize>,
-    full: bool,
-    verbose: bool,
-) -> Result<Option<String>> {
-    let verbose_str = if verbose { " -v" } else { "" };
-    if full {
-        return Ok(Some(format!("pytest{}", verbose_str)));
-    }
-    match line_no {
-        Some(ln) => {
-            let mut test_case = find_nearest(&filename, ln)?;
-            if let Some(t) = test_case.as_mut() {
-                let mut namespace_path = t
-                    .namespace
-                    .iter()
-                    .map(|x| x.values[1].to_string())
-                    .collect::<Vec<String>>()
-                    .join("::");
-                if let Some(tn) = t.name.as_mut() {
-                    if namespace_path.len() > 2 {
-                        namespace_path = format!(
-                            "{}::{}",
-                            namespace_path,
-                            tn.values[tn.values.len() - 1].to_string()
-                        );
-                    } else {
-                        namespace_path = format!("{}", tn.values[tn.values.len() - 1].to_string());
+    )
+}
+
+/// Triple quoted strings in the text as the (zero indexed) lines they
+/// start and end at
+fn triple_quoted(text: &str) -> Vec<(usize, usize)> {
+    let mut strings = vec![];
+    let mut open: Option<(usize, &str)> = None;
+    for (no, line) in text.lines().enumerate() {
+        let mut rest = line;
+        loop {
+            match open {
+                Some((start, quote)) => match rest.find(quote) {
+                    Some(i) => {
+                        strings.push((start, no));
+                        open = None;
+                        rest = &rest[i + 3..];
+                    }
+                    None => break,
+                },
+                None => {
+                    let next = ["\"\"\"", "'''", "#"]
+                        .iter()
+                        .filter_map(|q| rest.find(q).map(|i| (i, *q)))
+                        .min();
+                    match next {
+                        Some((i, q)) if q != "#" => {
+                            open = Some((no, q));
+                            rest = &rest[i + 3..];
+                        }
+                        _ => break,
                     }
                 }
-                // TODO: pick runner automatically
-                let comm = format!("pytest{} {}::{}", verbose_str, filename, namespace_path);
-                return Ok(Some(comm));
+            }
+        }
+    }
+    strings
+}
+
+/// If the line is within a docstring with `>>>` examples, returns the
+/// function or class it documents along with the classes it is nested
+/// in, outermost first. The list is empty for the module docstring.
+fn find_doctest_item(filename: &str, line_no: usize) -> Result<Option<Vec<Location>>> {
+    let text = fs::read_to_string(filename)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let current = line_no.saturating_sub(1);
+    let (start, end) = match triple_quoted(&text)
+        .into_iter()
+        .find(|(s, e)| *s <= current && current <= *e)
+    {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+    if !lines[start..=end].iter().any(|l| l.contains(">>>")) {
+        return Ok(None);
+    }
+
+    let code = |l: &&str| !l.trim().is_empty() && !l.trim_start().starts_with('#');
+    let previous = match lines[..start].iter().rposition(code) {
+        Some(p) => p,
+        None => return Ok(Some(vec![])),
+    };
+    if !lines[previous].trim_end().ends_with(':') {
+        return Ok(None);
+    }
+
+    // the signature could span multiple lines, so the closest def or
+    // class with a smaller indent is the one being documented
+    let item = Regex::new(r"^(\s*)(?:async\s+)?(?:def|class)\s+(\w+)").unwrap();
+    let mut chain = vec![];
+    let mut level = lines[start].len() - lines[start].trim_start().len();
+    for (i, line) in lines[..=previous].iter().enumerate().rev() {
+        if let Some(c) = item.captures(line) {
+            if c[1].len() < level {
+                level = c[1].len();
+                chain.push(Location {
+                    name: c[2].to_string(),
+                    line: i + 1,
+                });
+            }
+        }
+        if level == 0 {
+            break;
+        }
+    }
+    if chain.is_empty() {
+        return Ok(None);
+    }
+    chain.reverse();
+    Ok(Some(chain))
+}
+
+/// Dotted path of the module relative to the root it is imported from
+fn module_path(filename: &str, root: &str) -> Vec<String> {
+    Path::new(filename)
+        .with_extension("")
+        .strip_prefix(root)
+        .map(|x| x.iter().map(|c| c.to_string_lossy().to_string()).collect())
+        .unwrap_or_default()
+}
+
+fn find_all(filename: &str, names: &Names) -> Result<Vec<base::TestSpan>> {
+    base::find_all(
+        filename,
+        &names.test_regex(),
+        Some(&names.namespace_regex()),
+        base::Block::Indent,
+    )
+}
+
+/// Fill in the classes and the test from the test case found
+fn locate(mut command: Command, t: base::TestCase) -> Command {
+    command.scope = Scope::Namespace;
+    command.namespace = t
+        .namespace
+        .iter()
+        .map(|x| Location {
+            name: x.values[1].to_string(),
+            line: x.no,
+        })
+        .collect();
+    if let Some(tn) = t.name {
+        command.test = Some(Location {
+            name: tn.values[tn.values.len() - 1].to_string(),
+            line: tn.no,
+        });
+        command.scope = Scope::Test;
+    }
+    command
+}
+
+/// Find the file in the directory of the file or any of its parents
+fn find_upwards(filename: &str, name: &str) -> Option<PathBuf> {
+    Path::new(filename)
+        .ancestors()
+        .skip(1)
+        .map(|p| p.join(name))
+        .find(|p| p.is_file())
+}
+
+/// Config files pytest reads its options from along with the section
+/// it has to be in, in the order pytest looks for them
+const PYTEST_CONFIGS: [(&str, &str); 4] = [
+    ("pytest.ini", "[pytest]"),
+    ("pyproject.toml", "[tool.pytest.ini_options]"),
+    ("tox.ini", "[pytest]"),
+    ("setup.cfg", "[tool:pytest]"),
+];
+
+/// Closest pytest config file to the file. Files without a pytest
+/// section are skipped, except for `pytest.ini`.
+fn find_pytest_config(filename: &str) -> Option<PathBuf> {
+    for dir in Path::new(filename).ancestors().skip(1) {
+        for (name, section) in PYTEST_CONFIGS.iter() {
+            let path = dir.join(name);
+            let text = match fs::read_to_string(&path) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            if *name == "pytest.ini" || text.lines().any(|l| l.trim() == *section) {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// Directory of the config pytest picks its rootdir from, if there is
+/// any in the project
+fn config_dir(filename: &str) -> Option<PathBuf> {
+    let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+    find_pytest_config(filename)
+        .or_else(|| find_upwards(filename, "setup.py"))
+        // config files without a pytest section are used as a last resort
+        .or_else(|| {
+            dir.ancestors().find_map(|d| {
+                PYTEST_CONFIGS
+                    .iter()
+                    .map(|(n, _)| d.join(n))
+                    .find(|p| p.is_file())
+            })
+        })
+        .and_then(|p| p.parent().map(|x| x.to_path_buf()))
+}
+
+/// Directory pytest picks as its rootdir for the file, which node ids
+/// are relative to. Falls back to the directory of the file like pytest
+/// does when there is no config.
+fn rootdir(filename: &str) -> PathBuf {
+    config_dir(filename).unwrap_or_else(|| {
+        Path::new(filename)
+            .parent()
+            .unwrap_or(Path::new(""))
+            .to_path_buf()
+    })
+}
+
+/// Path of the file relative to the rootdir, as used in node ids
+fn node_path(filename: &str) -> String {
+    Path::new(filename)
+        .strip_prefix(rootdir(filename))
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|_| filename.to_string())
+}
+
+/// Value of an option in the pytest config, split into words
+fn pytest_option(filename: &str, key: &str) -> Option<Vec<String>> {
+    let path = find_pytest_config(filename)?;
+    let text = fs::read_to_string(&path).ok()?;
+    if path.ends_with("pyproject.toml") {
+        let value: toml::Value = toml::from_str(&text).ok()?;
+        return match value
+            .get("tool")?
+            .get("pytest")?
+            .get("ini_options")?
+            .get(key)?
+        {
+            toml::Value::String(s) => Some(s.split_whitespace().map(String::from).collect()),
+            toml::Value::Array(a) => Some(
+                a.iter()
+                    .filter_map(|x| x.as_str())
+                    .map(String::from)
+                    .collect(),
+            ),
+            _ => None,
+        };
+    }
+
+    // ini files, where values can continue on indented lines
+    let (_, section) = PYTEST_CONFIGS.iter().find(|(n, _)| path.ends_with(n))?;
+    let option = Regex::new(&format!(r"^{}\s*[=:](.*)", regex::escape(key))).unwrap();
+    let mut lines = text
+        .lines()
+        .skip_while(|l| l.trim() != *section)
+        .skip(1)
+        .take_while(|l| !l.starts_with('['));
+    let first = lines.by_ref().find_map(|l| option.captures(l))?;
+    let mut values: Vec<String> = first[1].split_whitespace().map(String::from).collect();
+    for line in lines.take_while(|l| l.starts_with([' ', '\t']) || l.is_empty()) {
+        values.extend(line.split_whitespace().map(String::from));
+    }
+    Some(values)
+}
+
+/// Runners other than pytest, all of which select tests using dotted
+/// module paths
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Runner {
+    Unittest,
+    Django,
+    Nose2,
+}
+
+/// Figure out the runner from the files in the project. Returns `None`
+/// for pytest, which is also what is used if nothing else matches.
+fn detect_runner(filename: &str) -> Option<Runner> {
+    let pytest =
+        find_pytest_config(filename).is_some() || find_upwards(filename, "conftest.py").is_some();
+    if pytest {
+        return None;
+    }
+    if find_upwards(filename, "manage.py").is_some() {
+        return Some(Runner::Django);
+    }
+    if find_upwards(filename, "nose2.cfg").is_some()
+        || find_upwards(filename, "unittest.cfg").is_some()
+    {
+        return Some(Runner::Nose2);
+    }
+    // unittest needs no config and so has to be found from the test
+    // cases in the file. Only importing it is not enough as pytest
+    // tests often use `unittest.mock`.
+    let imports = Regex::new(r"^\s*(import unittest\s*(#.*)?$|from unittest import\b)").unwrap();
+    let test_case = Regex::new(r"^\s*class \w+\s*\([^)]*\bTestCase\b").unwrap();
+    let text = fs::read_to_string(filename).unwrap_or_default();
+    if text.lines().any(|l| imports.is_match(l)) && text.lines().any(|l| test_case.is_match(l)) {
+        return Some(Runner::Unittest);
+    }
+    None
+}
+
+/// Tool managing the environment the tests have to be run in
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Uv,
+    Poetry,
+    Pipenv,
+    Hatch,
+    Tox,
+    /// a virtualenv in `.venv` or `venv`
+    Venv,
+    /// whatever is available in `PATH`
+    System,
+}
+
+fn find_venv(filename: &str) -> Option<PathBuf> {
+    Path::new(filename)
+        .ancestors()
+        .skip(1)
+        .flat_map(|p| vec![p.join(".venv"), p.join("venv")])
+        .find(|p| p.join("bin").is_dir())
+}
+
+/// Find the environment from the lock/config files closest to the file
+fn detect_environment(filename: &str) -> Option<Environment> {
+    let markers = [
+        ("uv.lock", Environment::Uv),
+        ("poetry.lock", Environment::Poetry),
+        ("Pipfile", Environment::Pipenv),
+        ("hatch.toml", Environment::Hatch),
+    ];
+    for dir in Path::new(filename).ancestors().skip(1) {
+        if let Some((_, env)) = markers.iter().find(|(m, _)| dir.join(m).is_file()) {
+            return Some(*env);
+        }
+        if dir.join(".venv/bin").is_dir() || dir.join("venv/bin").is_dir() {
+            return Some(Environment::Venv);
+        }
+    }
+    None
+}
+
+/// Update the command to run within the environment of the project
+fn in_environment(mut command: Command, environment: Option<Environment>) -> Command {
+    let prefix = match environment.or_else(|| detect_environment(&command.file)) {
+        Some(Environment::Uv) => "uv run",
+        Some(Environment::Poetry) => "poetry run",
+        Some(Environment::Pipenv) => "pipenv run",
+        Some(Environment::Hatch) => "hatch run",
+        // tox passes the arguments on to the command in its config
+        Some(Environment::Tox) => {
+            command.runner = vec!["tox".to_string(), "--".to_string()];
+            return command;
+        }
+        Some(Environment::Venv) => {
+            let venv = match find_venv(&command.file) {
+                Some(v) => v,
+                None => return command,
+            };
+            let bin = match &command.cwd {
+                Some(cwd) => venv.strip_prefix(cwd).unwrap_or(&venv).join("bin"),
+                None => venv.join("bin"),
             };
-            Ok(None)
+            // scripts like `./manage.py` have to be run with the python
+            // in the virtualenv
+            match command.runner.first() {
+                Some(program) if program.ends_with(".py") => {
+                    let python = bin.join("python").to_string_lossy().to_string();
+                    command.runner.insert(0, python);
+                }
+                Some(program) => {
+                    command.runner[0] = bin.join(program).to_string_lossy().to_string();
+                }
+                None => {}
+            }
+            return command;
         }
-        None => {
-            let comm = format!("pytest {}", filename,);
-            return Ok(Some(comm));
+        Some(Environment::System) | None => return command,
+    };
+    let mut runner: Vec<String> = prefix.split_whitespace().map(|x| x.to_string()).collect();
+    runner.append(&mut command.runner);
+    command.runner = runner;
+    command
+}
+
+/// Directory the dotted path of the module starts from, which is the
+/// one above the outermost package containing the file
+fn import_root(filename: &str) -> PathBuf {
+    let mut dir = Path::new(filename)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+    while dir.join("__init__.py").is_file() {
+        match dir.parent() {
+            Some(p) => dir = p.to_path_buf(),
+            None => break,
         }
     }
+    dir
+}
+
+/// Picker for projects using `unittest`, django's test runner or nose2
+pub struct Unittest {
+    runner: Runner,
+    environment: Option<Environment>,
+}
+
+impl Default for Unittest {
+    fn default() -> Unittest {
+        Unittest::new(&LanguageConfig::default())
+    }
+}
+
+impl Unittest {
+    pub fn new(config: &LanguageConfig) -> Unittest {
+        Unittest {
+            runner: Runner::Unittest,
+            environment: config.environment,
+        }
+    }
+
+    pub fn django(config: &LanguageConfig) -> Unittest {
+        Unittest {
+            runner: Runner::Django,
+            ..Unittest::new(config)
+        }
+    }
+
+    pub fn nose2(config: &LanguageConfig) -> Unittest {
+        Unittest {
+            runner: Runner::Nose2,
+            ..Unittest::new(config)
+        }
+    }
+
+    /// Commands are run from the directory the module paths start
+    /// from, which is where `manage.py` is in django projects
+    fn new_command(&self, filename: &str, verbose: bool) -> Command {
+        let root = match self.runner {
+            Runner::Django => find_upwards(filename, "manage.py")
+                .and_then(|x| x.parent().map(|p| p.to_path_buf()))
+                .unwrap_or_default(),
+            _ => import_root(filename),
+        };
+        let runner = match self.runner {
+            Runner::Unittest => "python -m unittest",
+            Runner::Django => "./manage.py test",
+            Runner::Nose2 => "nose2",
+        };
+        let root = root.to_string_lossy().to_string();
+        let mut command = Command::new(runner, &root, filename);
+        if !root.is_empty() {
+            command.cwd = Some(root);
+        }
+        command.module = module_path(filename, &command.root);
+        let command = in_environment(command, self.environment);
+        match (verbose, self.runner) {
+            // django takes the verbosity level as a value
+            (true, Runner::Django) => command.arg("-v").arg("2"),
+            _ => command.verbose(verbose),
+        }
+    }
+}
+
+impl Picker for Unittest {
+    fn language(&self) -> &'static str {
+        "python"
+    }
+
+    fn framework(&self) -> &'static str {
+        match self.runner {
+            Runner::Unittest => "unittest",
+            Runner::Django => "django",
+            Runner::Nose2 => "nose2",
+        }
+    }
+
+    fn detect(&self, filename: &str) -> bool {
+        filename.ends_with(".py") && detect_runner(filename) == Some(self.runner)
+    }
+
+    fn find_nearest(&self, filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
+        find_nearest(filename, line_no, &Names::default())
+    }
+
+    fn find_all(&self, filename: &str) -> Result<Vec<base::TestSpan>> {
+        find_all(filename, &Names::default())
+    }
+
+    fn suite_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        let mut command = self.new_command(filename, verbose).scope(Scope::Suite);
+        command.module.clear();
+        Ok(Some(command))
+    }
+
+    fn file_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        let command = self.new_command(filename, verbose);
+        let module = command.module.join(".");
+        Ok(Some(command.arg(&module)))
+    }
+
+    fn nearest_command(
+        &self,
+        filename: &str,
+        line_no: usize,
+        verbose: bool,
+    ) -> Result<Option<Command>> {
+        // doctests can only be selected per file
+        if find_doctest_item(filename, line_no)?.is_some() {
+            let root = import_root(filename).to_string_lossy().to_string();
+            let command = Command::new("python -m doctest", &root, filename).scope(Scope::File);
+            let command = in_environment(command, self.environment);
+            return Ok(Some(command.arg("-v").arg(filename)));
+        }
+
+        let test_case = self.find_nearest(filename, line_no)?;
+        if let Some(t) = test_case {
+            let command = locate(self.new_command(filename, verbose), t);
+            let mut path = command.namespace_path();
+            path.extend(command.test.iter().map(|x| x.name.to_string()));
+            return Ok(Some(command.arg(&path.join("."))));
+        }
+        Ok(None)
+    }
+}
+
+const PARAMETRIZE_REGEX: &str = r"^\s*@(?:pytest\.)?(?:mark\.)?parametrize\s*\(";
+
+/// Brackets and commas outside of strings and comments along with their
+/// offset and how deeply nested they are
+fn structure(text: &str) -> Vec<(usize, char, usize)> {
+    let mut items = vec![];
+    let mut depth: usize = 0;
+    let mut quote = None;
+    let mut comment = false;
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if comment {
+            comment = c != '\n';
+            continue;
+        }
+        if let Some(q) = quote {
+            if c == '\\' {
+                chars.next();
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '\'' | '"' => quote = Some(c),
+            '#' => comment = true,
+            '(' | '[' | '{' => {
+                items.push((i, c, depth));
+                depth += 1;
+            }
+            ')' | ']' | '}' => {
+                depth = depth.saturating_sub(1);
+                items.push((i, c, depth));
+            }
+            ',' => items.push((i, c, depth)),
+            _ => {}
+        }
+    }
+    items
+}
+
+/// Contents of the brackets opening at the start of the text along with
+/// the offset they start at
+fn bracket_contents(text: &str) -> Option<(usize, &str)> {
+    let s = structure(text);
+    let (start, _, depth) = s.first().filter(|(i, _, _)| *i == 0)?;
+    let (end, _, _) = s
+        .iter()
+        .skip(1)
+        .find(|(_, c, d)| d == depth && ")]}".contains(*c))?;
+    Some((start + 1, &text[start + 1..*end]))
+}
+
+/// Split the text at the commas not nested within brackets. Parts are
+/// trimmed and come with the offset they start at.
+fn split_args(text: &str) -> Vec<(usize, &str)> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let commas = structure(text)
+        .into_iter()
+        .filter(|(_, c, d)| *c == ',' && *d == 0)
+        .map(|(i, _, _)| i);
+    for end in commas.chain(std::iter::once(text.len())) {
+        let part = &text[start..end];
+        let mut trimmed = part.trim_start();
+        // comments after the comma belong to the previous part
+        while trimmed.starts_with('#') {
+            trimmed = trimmed
+                .split_once('\n')
+                .map(|x| x.1)
+                .unwrap_or("")
+                .trim_start();
+        }
+        if !trimmed.trim().is_empty() {
+            parts.push((start + part.len() - trimmed.len(), trimmed.trim_end()));
+        }
+        start = end + 1;
+    }
+    parts
+}
+
+/// Split out `name=value` keyword arguments from the positional ones
+fn keyword(arg: &str) -> Option<(&str, &str)> {
+    let re = Regex::new(r"^(\w+)\s*=[^=]").unwrap();
+    let name = re.captures(arg)?.get(1)?.as_str();
+    Some((name, arg[name.len()..].trim_start()[1..].trim()))
+}
+
+fn string_literal(value: &str) -> Option<String> {
+    let value = value.strip_prefix('r').unwrap_or(value);
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let inner = value.strip_prefix(quote)?.strip_suffix(quote)?;
+    // pytest escapes anything that is not printable ascii
+    if inner.contains(['\\', quote]) || !inner.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return None;
+    }
+    Some(inner.to_string())
+}
+
+/// ID pytest generates for a value in the parametrize args. Returns
+/// `None` if it cannot be figured out without evaluating the value.
+fn value_id(value: &str, argname: &str, index: usize) -> Option<String> {
+    if let Some(s) = string_literal(value) {
+        return Some(s);
+    }
+    if ["True", "False", "None"].contains(&value) || value.parse::<i64>().is_ok() {
+        return Some(value.to_string());
+    }
+    if Regex::new(r"^-?\d+\.\d+$").unwrap().is_match(value) {
+        // python prints floats without the trailing zeros
+        let (int, frac) = value.split_once('.')?;
+        let frac = frac.trim_end_matches('0');
+        return Some(format!(
+            "{}.{}",
+            int,
+            if frac.is_empty() { "0" } else { frac }
+        ));
+    }
+    // containers are never stringified
+    if value.starts_with(['[', '(', '{']) && bracket_contents(value).is_some() {
+        return Some(format!("{}{}", argname, index));
+    }
+    None
+}
+
+/// A case in the args of `pytest.mark.parametrize`, `None` as the id if
+/// it could not be figured out
+struct ParamCase {
+    /// line of the `def` of the parametrized test (zero indexed)
+    def_line: usize,
+    id: Option<String>,
+}
+
+/// If the line is within a `pytest.mark.parametrize` decorator, find the
+/// test it is for and the id of the case on the line
+fn find_param_case(filename: &str, line_no: usize) -> Result<Option<ParamCase>> {
+    let text = fs::read_to_string(filename)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let decorator = Regex::new(PARAMETRIZE_REGEX).unwrap();
+    if line_no == 0 || line_no > lines.len() {
+        return Ok(None);
+    }
+    let current = line_no - 1;
+    let start = match (0..=current)
+        .rev()
+        .take_while(|i| *i == current || !lines[*i].trim_start().starts_with("def "))
+        .find(|i| decorator.is_match(lines[*i]))
+    {
+        Some(s) => s,
+        None => return Ok(None),
+    };
+
+    // offsets are from the start of the decorator line
+    let rest = lines[start..].join("\n");
+    let open = decorator.find(&rest).map(|m| m.end() - 1).unwrap_or(0);
+    let (args_start, args) = match bracket_contents(&rest[open..]) {
+        Some((s, a)) => (open + s, a),
+        None => return Ok(None),
+    };
+    let line_of = |offset: usize| start + rest[..offset].matches('\n').count();
+    let end_line = line_of(args_start + args.len());
+    if current > end_line {
+        return Ok(None);
+    }
+    let def_line = match base::find_next(filename, r"^\s*(?:async )?def \w+", end_line)? {
+        Some(d) => d.no,
+        None => return Ok(None),
+    };
+    let no_id = Ok(Some(ParamCase { def_line, id: None }));
+
+    // ids of stacked parametrize decorators are combined, which is not
+    // worth replicating
+    let def = Regex::new(r"^\s*(async\s+)?(def|class)\b").unwrap();
+    let mut stacked = 0;
+    for i in (0..def_line).rev().take_while(|i| !def.is_match(lines[*i])) {
+        if decorator.is_match(lines[i]) && decorated_def(filename, i + 1)? == Some(def_line + 1) {
+            stacked += 1;
+        }
+    }
+    if stacked > 1 {
+        return no_id;
+    }
+
+    let mut positional = vec![];
+    let mut ids = None;
+    for (offset, arg) in split_args(args) {
+        match keyword(arg) {
+            Some(("argnames", v)) | Some(("argvalues", v)) => {
+                positional.push((offset + arg.len() - v.len(), v))
+            }
+            Some(("ids", v)) => ids = Some(v),
+            Some(_) => {}
+            None => positional.push((offset, arg)),
+        }
+    }
+    // ids generated by functions cannot be worked out
+    if ids.is_some_and(|x| x != "None" && bracket_contents(x).is_none()) {
+        return no_id;
+    }
+    let (argnames, (values_start, argvalues)) = match positional.as_slice() {
+        [(_, n), v, ..] => (*n, *v),
+        _ => return no_id,
+    };
+    let argnames: Vec<String> = match string_literal(argnames) {
+        Some(n) => n.split(',').map(|x| x.trim().to_string()).collect(),
+        None => match bracket_contents(argnames) {
+            Some((_, n)) => split_args(n)
+                .iter()
+                .filter_map(|(_, x)| string_literal(x))
+                .collect(),
+            None => return no_id,
+        },
+    };
+    let (cases_start, cases) = match bracket_contents(argvalues) {
+        Some((s, c)) => (args_start + values_start + s, split_args(c)),
+        None => return no_id,
+    };
+
+    // the case has to be the only one on the line
+    let on_line: Vec<usize> = (0..cases.len())
+        .filter(|i| {
+            let (offset, case) = cases[*i];
+            let offset = cases_start + offset;
+            line_of(offset) <= current && current <= line_of(offset + case.len())
+        })
+        .collect();
+    let index = match on_line.as_slice() {
+        [i] => *i,
+        _ => return no_id,
+    };
+
+    let ids: Option<Vec<&str>> = ids
+        .and_then(bracket_contents)
+        .map(|(_, x)| split_args(x).into_iter().map(|(_, x)| x).collect());
+    let case_id = |i: usize| -> Option<String> {
+        let mut case = cases[i].1;
+        if let Some(id) = ids.as_ref().and_then(|x| x.get(i)) {
+            if *id != "None" {
+                return string_literal(id);
+            }
+        }
+        if let Some(param) = case
+            .strip_prefix("pytest.param")
+            .or_else(|| case.strip_prefix("param"))
+        {
+            let (_, inner) = bracket_contents(param.trim_start())?;
+            let mut values = vec![];
+            for (_, arg) in split_args(inner) {
+                match keyword(arg) {
+                    Some(("id", v)) => return string_literal(v),
+                    Some(_) => {}
+                    None => values.push(arg),
+                }
+            }
+            return values
+                .iter()
+                .zip(argnames.iter())
+                .map(|(v, n)| value_id(v, n, i))
+                .collect::<Option<Vec<_>>>()
+                .map(|x| x.join("-"));
+        }
+        let values = if argnames.len() == 1 {
+            vec![case]
+        } else {
+            case = case.strip_prefix(['(', '['])?.strip_suffix([')', ']'])?;
+            split_args(case).into_iter().map(|(_, x)| x).collect()
+        };
+        if values.len() != argnames.len() {
+            return None;
+        }
+        values
+            .iter()
+            .zip(argnames.iter())
+            .map(|(v, n)| value_id(v, n, i))
+            .collect::<Option<Vec<_>>>()
+            .map(|x| x.join("-"))
+    };
+
+    // pytest makes duplicate ids unique, which is not worth replicating
+    let id = case_id(index);
+    let duplicate = (0..cases.len()).any(|i| i != index && id.is_some() && case_id(i) == id);
+    Ok(Some(ParamCase {
+        def_line,
+        id: id.filter(|_| !duplicate),
+    }))
+}
+
+pub struct Pytest {
+    environment: Option<Environment>,
+}
+
+impl Pytest {
+    pub fn new(config: &LanguageConfig) -> Pytest {
+        Pytest {
+            environment: config.environment,
+        }
+    }
+
+    /// Command for the project of the file, run from the directory given
+    fn new_command(&self, filename: &str, cwd: Option<PathBuf>) -> Command {
+        let root = rootdir(filename).to_string_lossy().to_string();
+        let mut command = Command::new("pytest", &root, filename);
+        command.cwd = cwd
+            .map(|x| x.to_string_lossy().to_string())
+            .filter(|x| !x.is_empty());
+        in_environment(command, self.environment)
+    }
+}
+
+impl Default for Pytest {
+    fn default() -> Pytest {
+        Pytest::new(&LanguageConfig::default())
+    }
+}
+
+impl Picker for Pytest {
+    fn language(&self) -> &'static str {
+        "python"
+    }
+
+    fn framework(&self) -> &'static str {
+        "pytest"
+    }
+
+    fn detect(&self, filename: &str) -> bool {
+        filename.ends_with(".py")
+    }
+
+    fn find_nearest(&self, filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
+        find_nearest(filename, line_no, &Names::from_config(filename))
+    }
+
+    fn find_all(&self, filename: &str) -> Result<Vec<base::TestSpan>> {
+        find_all(filename, &Names::from_config(filename))
+    }
+
+    fn suite_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        // the whole suite is only collected from the project root
+        let command = self.new_command(filename, config_dir(filename));
+        Ok(Some(command.scope(Scope::Suite).verbose(verbose)))
+    }
+
+    fn file_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        Ok(Some(
+            self.new_command(filename, Some(rootdir(filename)))
+                .verbose(verbose)
+                .arg(&node_path(filename)),
+        ))
+    }
+
+    fn nearest_command(
+        &self,
+        filename: &str,
+        line_no: usize,
+        verbose: bool,
+    ) -> Result<Option<Command>> {
+        if let Some(mut chain) = find_doctest_item(filename, line_no)? {
+            let mut command = self
+                .new_command(filename, Some(rootdir(filename)))
+                .scope(Scope::Test)
+                .verbose(verbose);
+            command.module = module_path(filename, &import_root(filename).to_string_lossy());
+            command.test = chain.pop();
+            command.namespace = chain;
+            let mut path = command.namespace_path();
+            path.extend(command.test.iter().map(|x| x.name.to_string()));
+            let node_id = format!("{}::{}", node_path(filename), path.join("."));
+            return Ok(Some(command.arg("--doctest-modules").arg(&node_id)));
+        }
+
+        // cases of parametrized tests are run using their id
+        let case = find_param_case(filename, line_no)?;
+        let test_case = match &case {
+            Some(c) => self.find_nearest(filename, c.def_line + 1)?,
+            None => self.find_nearest(filename, line_no)?,
+        };
+        if let Some(t) = test_case {
+            let mut command = locate(self.new_command(filename, Some(rootdir(filename))), t);
+            if let (Some(test), Some(id)) = (command.test.as_mut(), case.and_then(|c| c.id)) {
+                test.name = format!("{}[{}]", test.name, id);
+            }
+
+            let mut node_id = vec![node_path(filename)];
+            node_id.extend(command.namespace_path());
+            node_id.extend(command.test.iter().map(|x| x.name.to_string()));
+            return Ok(Some(command.verbose(verbose).arg(&node_id.join("::"))));
+        };
+        Ok(None)
+    }
 }
 
 #[cfg(test)]
@@ -62,7 +1029,8 @@ mod tests {
 
     #[test]
     fn test_simple_thingy() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 16)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 16)
             .unwrap()
             .unwrap();
 
@@ -73,23 +1041,22 @@ mod tests {
 
     #[test]
     fn test_simple_command() {
-        let resp = get_command(
-            "./fixtures/python/pytest/test_stuff.py",
-            Some(16),
-            false,
-            false,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(
-            resp,
-            "pytest ./fixtures/python/pytest/test_stuff.py::test_function"
-        );
+        let resp = Pytest::default()
+            .get_command(
+                "./fixtures/python/pytest/test_stuff.py",
+                Some(16),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "pytest test_stuff.py::test_function");
     }
 
     #[test]
     fn test_simple_on_def() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 15)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 15)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 15);
@@ -99,7 +1066,8 @@ mod tests {
 
     #[test]
     fn test_simple_on_async_def() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 20)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 20)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 19);
@@ -112,23 +1080,25 @@ mod tests {
 
     #[test]
     fn test_simple_async_def_command() {
-        let resp = get_command(
-            "./fixtures/python/pytest/test_stuff.py",
-            Some(20),
-            false,
-            false,
-        )
-        .unwrap()
-        .unwrap();
+        let resp = Pytest::default()
+            .get_command(
+                "./fixtures/python/pytest/test_stuff.py",
+                Some(20),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
         assert_eq!(
-            resp,
-            "pytest ./fixtures/python/pytest/test_stuff.py::test_async_function"
+            resp.to_string(),
+            "pytest test_stuff.py::test_async_function"
         );
     }
 
     #[test]
     fn test_simple_on_empty() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 14)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 14)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 11);
@@ -138,16 +1108,18 @@ mod tests {
 
     #[test]
     fn test_simple_on_class() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 10)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 10)
             .unwrap()
             .unwrap();
-        assert_eq!(resp.name.is_none(), true);
+        assert!(resp.name.is_none());
         assert_eq!(resp.namespace.len(), 1);
     }
 
     #[test]
     fn test_method_obj() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 12)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 12)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 11);
@@ -159,7 +1131,8 @@ mod tests {
 
     #[test]
     fn test_method_out_nested() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 6)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 6)
             .unwrap()
             .unwrap();
         println!("{:?}", resp.clone());
@@ -172,7 +1145,8 @@ mod tests {
 
     #[test]
     fn test_nested() {
-        let resp = find_nearest("./fixtures/python/pytest/test_stuff.py", 4)
+        let resp = Pytest::default()
+            .find_nearest("./fixtures/python/pytest/test_stuff.py", 4)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 3);
@@ -189,44 +1163,389 @@ mod tests {
 
     #[test]
     fn test_nested_command_normal() {
-        let resp = get_command(
-            "./fixtures/python/pytest/test_stuff.py",
-            Some(4),
-            false,
-            false,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "pytest ./fixtures/python/pytest/test_stuff.py::TestClass::TestNestedClass::test_nestedclass_method");
+        let resp = Pytest::default()
+            .get_command(
+                "./fixtures/python/pytest/test_stuff.py",
+                Some(4),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "pytest test_stuff.py::TestClass::TestNestedClass::test_nestedclass_method"
+        );
     }
 
     #[test]
     fn test_full_command_normal() {
         // (format "toffee '%s'" (string-replace default-directory "" (buffer-file-name)))
-        let resp = get_command("./fixtures/python/pytest/test_stuff.py", None, true, false)
+        let resp = Pytest::default()
+            .get_command("./fixtures/python/pytest/test_stuff.py", None, true, false)
             .unwrap()
             .unwrap();
-        assert_eq!(resp, "pytest");
+        assert_eq!(resp.to_string(), "pytest");
     }
 
     #[test]
     fn test_nested_command_verbose() {
-        let resp = get_command(
-            "./fixtures/python/pytest/test_stuff.py",
-            Some(4),
-            false,
-            true,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "pytest -v ./fixtures/python/pytest/test_stuff.py::TestClass::TestNestedClass::test_nestedclass_method");
+        let resp = Pytest::default()
+            .get_command(
+                "./fixtures/python/pytest/test_stuff.py",
+                Some(4),
+                false,
+                true,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "pytest -v test_stuff.py::TestClass::TestNestedClass::test_nestedclass_method"
+        );
+    }
+
+    #[test]
+    fn test_detect_runner() {
+        let detect = |f| detect_runner(f);
+        assert_eq!(
+            detect("./fixtures/python/unittest/pkg/tests/test_math.py"),
+            Some(Runner::Unittest)
+        );
+        assert_eq!(
+            detect("./fixtures/python/django/app/tests.py"),
+            Some(Runner::Django)
+        );
+        assert_eq!(
+            detect("./fixtures/python/nose2/tests/test_nose.py"),
+            Some(Runner::Nose2)
+        );
+        assert_eq!(detect("./fixtures/python/pytest/test_stuff.py"), None);
+        assert_eq!(detect("./fixtures/python/mocked/test_mocked.py"), None);
+    }
+
+    #[test]
+    fn test_unittest_commands() {
+        let filename = "./fixtures/python/unittest/pkg/tests/test_math.py";
+        let resp = Unittest::default()
+            .get_command(filename, Some(9), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "python -m unittest pkg.tests.test_math.TestMath.test_sub"
+        );
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/python/unittest"));
+
+        let resp = Unittest::default()
+            .get_command(filename, None, false, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "python -m unittest -v pkg.tests.test_math"
+        );
+
+        let resp = Unittest::default()
+            .get_command(filename, None, true, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "python -m unittest");
+    }
+
+    #[test]
+    fn test_django_commands() {
+        let resp = Unittest::django(&LanguageConfig::default())
+            .get_command(
+                "./fixtures/python/django/app/tests.py",
+                Some(6),
+                false,
+                true,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "./manage.py test -v 2 app.tests.TestViews.test_index"
+        );
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/python/django"));
+    }
+
+    #[test]
+    fn test_nose2_commands() {
+        let resp = Unittest::nose2(&LanguageConfig::default())
+            .get_command(
+                "./fixtures/python/nose2/tests/test_nose.py",
+                Some(4),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "nose2 tests.test_nose.TestNose");
+    }
+
+    #[test]
+    fn test_detect_environment() {
+        let detect = |f| detect_environment(f);
+        assert_eq!(
+            detect("./fixtures/python/environments/uv/test_env.py"),
+            Some(Environment::Uv)
+        );
+        assert_eq!(
+            detect("./fixtures/python/environments/poetry/tests/test_env.py"),
+            Some(Environment::Poetry)
+        );
+        assert_eq!(
+            detect("./fixtures/python/environments/venv/test_env.py"),
+            Some(Environment::Venv)
+        );
+        assert_eq!(detect("./fixtures/python/pytest/test_stuff.py"), None);
+        assert_eq!(detect("./fixtures/python/mocked/test_mocked.py"), None);
+    }
+
+    #[test]
+    fn test_environment_commands() {
+        let command = |f: &str, config: &LanguageConfig| {
+            Pytest::new(config)
+                .get_command(f, None, false, false)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        let default = LanguageConfig::default();
+        assert_eq!(
+            command("./fixtures/python/environments/uv/test_env.py", &default),
+            "uv run pytest test_env.py"
+        );
+        assert_eq!(
+            command("./fixtures/python/environments/venv/test_env.py", &default),
+            ".venv/bin/pytest test_env.py"
+        );
+
+        // config takes precedence over what is detected
+        let config = |environment| LanguageConfig {
+            environment: Some(environment),
+            ..LanguageConfig::default()
+        };
+        assert_eq!(
+            command(
+                "./fixtures/python/environments/uv/test_env.py",
+                &config(Environment::System)
+            ),
+            "pytest test_env.py"
+        );
+        assert_eq!(
+            command(
+                "./fixtures/python/environments/uv/test_env.py",
+                &config(Environment::Tox)
+            ),
+            "tox -- test_env.py"
+        );
+    }
+
+    #[test]
+    fn test_environment_scripts() {
+        let mut comm = Command::new(
+            "./manage.py test",
+            "fixtures/python/environments/venv",
+            "fixtures/python/environments/venv/test_env.py",
+        );
+        comm.cwd = Some("fixtures/python/environments/venv".to_string());
+        let comm = in_environment(comm, Some(Environment::Venv));
+        assert_eq!(comm.to_string(), ".venv/bin/python ./manage.py test");
+    }
+
+    #[test]
+    fn test_decorators_and_signatures() {
+        let nearest = |filename, ln| {
+            let resp = Pytest::default()
+                .find_nearest(filename, ln)
+                .unwrap()
+                .unwrap()
+                .name
+                .unwrap();
+            (resp.no, resp.values.last().unwrap().to_string())
+        };
+        let filename = "./fixtures/python/pytest/test_syntax.py";
+        assert_eq!(nearest(filename, 8), (10, "test_decorated".to_string()));
+        assert_eq!(nearest(filename, 9), (10, "test_decorated".to_string()));
+        assert_eq!(nearest(filename, 16), (14, "test_multiline".to_string()));
+        let filename = "./fixtures/python/prefixes/check_prefixes.py";
+        assert_eq!(nearest(filename, 14), (17, "addition_spec".to_string()));
+    }
+
+    #[test]
+    fn test_custom_prefixes() {
+        let command = |filename, ln| {
+            Pytest::default()
+                .get_command(filename, Some(ln), false, false)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        let filename = "./fixtures/python/prefixes/check_prefixes.py";
+        assert_eq!(
+            command(filename, 6),
+            "pytest check_prefixes.py::SuiteMath::check_add"
+        );
+        assert_eq!(
+            command(filename, 18),
+            "pytest check_prefixes.py::addition_spec"
+        );
+        let filename = "./fixtures/python/prefixes/nested/test_nested.py";
+        assert_eq!(command(filename, 2), "pytest test_nested.py::should_work");
+    }
+
+    #[test]
+    fn test_rootdir_relative_node_ids() {
+        let filename = "./fixtures/python/rootdir/tests/unit/test_root.py";
+        let resp = Pytest::default()
+            .get_command(filename, Some(2), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "pytest tests/unit/test_root.py::test_root"
+        );
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/python/rootdir"));
+
+        let absolute = std::env::current_dir().unwrap().join(filename);
+        let resp = Pytest::default()
+            .get_command(&absolute.to_string_lossy(), Some(2), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "pytest tests/unit/test_root.py::test_root"
+        );
+        assert!(resp.cwd.unwrap().ends_with("fixtures/python/rootdir"));
+    }
+
+    #[test]
+    fn test_parametrize_cases() {
+        let command = |ln| {
+            let resp = Pytest::default()
+                .get_command(
+                    "./fixtures/python/pytest/test_params.py",
+                    Some(ln),
+                    false,
+                    false,
+                )
+                .unwrap()
+                .unwrap();
+            resp.args[0]
+                .trim_start_matches("test_params.py::")
+                .to_string()
+        };
+        assert_eq!(command(7), "test_increment[1-2]");
+        assert_eq!(command(8), "test_increment[a-b]");
+        assert_eq!(command(9), "test_increment[three]");
+        assert_eq!(command(10), "test_increment[value3-2]");
+        // values which need to be evaluated run all the cases
+        assert_eq!(command(11), "test_increment");
+        assert_eq!(command(5), "test_increment");
+        assert_eq!(command(21), "test_flag[on]");
+        assert_eq!(command(22), "test_flag[off]");
+        assert_eq!(command(32), "TestParams::test_number[1.5]");
+        assert_eq!(command(33), "TestParams::test_number[-2]");
+        assert_eq!(command(31), "TestParams::test_number");
+        assert_eq!(command(36), "TestParams::test_number");
+        // ids from functions and stacked decorators are not worked out
+        assert_eq!(command(43), "test_ids_function");
+        assert_eq!(command(52), "test_stacked");
+        assert_eq!(command(54), "test_stacked");
+    }
+
+    #[test]
+    fn test_doctest_commands() {
+        let filename = "./fixtures/python/pytest/doctests.py";
+        let command = |ln| {
+            Pytest::default()
+                .get_command(filename, Some(ln), false, false)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(command(3), "pytest --doctest-modules doctests.py::doctests");
+        assert_eq!(
+            command(11),
+            "pytest --doctest-modules doctests.py::doctests.add"
+        );
+        assert_eq!(
+            command(22),
+            "pytest --doctest-modules doctests.py::doctests.Counter.increment"
+        );
+
+        let filename = "./fixtures/python/django/app/utils.py";
+        let resp = Unittest::django(&LanguageConfig::default())
+            .get_command(filename, Some(3), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            format!("python -m doctest -v {}", filename)
+        );
+    }
+
+    #[test]
+    fn test_doctest_outside_examples() {
+        let filename = "./fixtures/python/pytest/doctests.py";
+        assert!(find_doctest_item(filename, 14).unwrap().is_none());
+        assert!(find_doctest_item(filename, 18).unwrap().is_none());
+        assert!(find_doctest_item(filename, 29).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_empty_file() {
+        let filename = "./fixtures/python/pytest/test_empty.py";
+        assert!(find_param_case(filename, 1).unwrap().is_none());
+        assert!(find_param_case(filename, 3).unwrap().is_none());
+        assert!(decorated_def(filename, 1).unwrap().is_none());
+        assert!(Pytest::default()
+            .get_command(filename, Some(1), false, false)
+            .unwrap()
+            .is_none());
+        assert!(Unittest::django(&LanguageConfig::default())
+            .get_command(
+                "./fixtures/python/django/app/test_empty.py",
+                Some(1),
+                false,
+                false
+            )
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_split_args() {
+        let args = split_args(r#" "a,b", [(1, ")"), 2], ids=None "#);
+        let parts: Vec<&str> = args.iter().map(|(_, x)| *x).collect();
+        assert_eq!(parts, vec![r#""a,b""#, r#"[(1, ")"), 2]"#, "ids=None"]);
+        assert_eq!(args[1].0, 8);
     }
 
     #[test]
     fn test_full_command_verbose() {
-        let resp = get_command("./fixtures/python/pytest/test_stuff.py", None, true, true)
+        let resp = Pytest::default()
+            .get_command("./fixtures/python/pytest/test_stuff.py", None, true, true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "pytest -v");
+        assert_eq!(resp.to_json()["scope"], "suite");
+        // without a config the suite is run from where toffee was run
+        assert_eq!(resp.cwd, None);
+        assert_eq!(resp.root, "./fixtures/python/pytest");
+
+        let resp = Pytest::default()
+            .get_command(
+                "./fixtures/python/rootdir/tests/unit/test_root.py",
+                None,
+                true,
+                false,
+            )
             .unwrap()
             .unwrap();
-        assert_eq!(resp, "pytest -v");
+        assert_eq!(resp.cwd.as_deref(), Some("./fixtures/python/rootdir"));
     }
 }
@@ -1,90 +1,565 @@
 use super::base;
+#[cfg(feature = "tree-sitter")]
+use super::treesitter;
+use super::{Command, Location, Picker, Scope};
+use crate::config::LanguageConfig;
 
 use anyhow::anyhow;
 use anyhow::Result;
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
 
-fn find_nearest_test_markers(filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
-    Ok(base::find_nearest(
-        &filename,
-        r"^\s*#\[test\]",
-        Some(r"^\s*#\[cfg\(test\)\]"),
-        line_no,
-        false,
-    )?)
-}
+const MOD_REGEX: &str = r"^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)";
 
 fn find_nearest_namespace(filename: &str, line_no: usize) -> Result<Option<base::WithLineNo>> {
-    Ok(base::find_next(
-        &filename,
-        r"^\s*mod ([a-z_0-9]+)",
-        line_no,
-    )?)
+    base::find_next(filename, MOD_REGEX, line_no)
+}
+
+const IMPL_REGEX: &str = r"^\s*(?:unsafe\s+)?impl\b(?:<[^>]*>)?[^{;]*?(?:for\s+)?(?:\w+::)*(\w+)(?:<[^{;]*>)?(?:\s+where\b[^{;]*)?\s*\{";
+
+/// Inline modules that are still open at the start of the given (1
+/// indexed) line, outermost first
+fn module_chain(filename: &str, line_no: usize) -> Result<Vec<Location>> {
+    #[cfg(feature = "tree-sitter")]
+    if let Ok(m) = treesitter::rust_modules(filename, line_no) {
+        return Ok(m);
+    }
+    base::open_blocks(filename, line_no, &[&format!(r"{}\s*\{{", MOD_REGEX)])
+}
+
+/// If the line is within a code block in the `///` comments of an
+/// item, returns the item documented by it
+fn find_doctest_item(filename: &str, line_no: usize) -> Result<Option<Location>> {
+    let text = fs::read_to_string(filename)?;
+    let lines: Vec<&str> = text.lines().collect();
+    let is_doc = |line: &str| {
+        let line = line.trim_start();
+        line.starts_with("///") && !line.starts_with("////")
+    };
+    let is_fence = |line: &str| line.trim_start()[3..].trim_start().starts_with("```");
+    let current = match line_no.checked_sub(1).and_then(|i| lines.get(i)) {
+        Some(l) if is_doc(l) => line_no - 1,
+        _ => return Ok(None),
+    };
+
+    let start = (0..current)
+        .rev()
+        .take_while(|i| is_doc(lines[*i]))
+        .last()
+        .unwrap_or(current);
+    let fences = lines[start..current].iter().filter(|l| is_fence(l)).count();
+    if fences % 2 == 0 && !is_fence(lines[current]) {
+        return Ok(None);
+    }
+
+    let item = Regex::new(
+        r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:default\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+"[^"]*"\s+)?(?:fn|struct|enum|union|trait|type|mod|const|static|macro_rules!)\s+(\w+)"#,
+    )
+    .unwrap();
+    for (i, line) in lines.iter().enumerate().skip(current) {
+        let trimmed = line.trim_start();
+        if is_doc(line) || trimmed.starts_with("#[") || trimmed.is_empty() {
+            continue;
+        }
+        return Ok(item.captures(line).map(|c| Location {
+            name: c[1].to_string(),
+            line: i + 1,
+        }));
+    }
+    Ok(None)
 }
 
+/// Finds the first function after the line, skipping over any other
+/// attributes stacked on top of it
 fn find_nearest_test_function(filename: &str, line_no: usize) -> Result<Option<base::WithLineNo>> {
-    Ok(base::find_next(
-        &filename,
-        r"^\s*fn test_([a-z_0-9]*)",
+    base::find_next(
+        filename,
+        r#"^\s*(?:pub(?:\([^)]*\))?\s+)?(?:const\s+)?(?:async\s+)?(?:unsafe\s+)?(?:extern\s+"[^"]*"\s+)?fn\s+(\w+)"#,
         line_no,
-    )?)
+    )
+}
+
+/// Cargo target that a file is a part of
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    /// library and the default binary in `src/`
+    Src,
+    Bin(String),
+    Test(String),
+    Bench(String),
+    Example(String),
 }
 
-pub fn get_command(
-    filename: &str,
-    line_no: Option<usize>,
-    full: bool,
-    verbose: bool,
-) -> Result<Option<String>> {
-    let verbose_str = if verbose { " -v" } else { "" };
-    if full {
-        return Ok(Some(format!("cargo test{}", verbose_str)));
+impl Target {
+    /// Binary selection for nextest which, unlike `cargo test`, needs
+    /// `--lib` or `--bins` to be explicit about tests in `src/`
+    fn nextest_args(&self, filename: &str) -> Vec<String> {
+        if *self != Target::Src {
+            return self.args();
+        }
+        let root = Path::new(base::get_project_root(filename, "Cargo.toml"));
+        let is_main = Path::new(filename) == root.join("src/main.rs");
+        let flag = if root.join("src/lib.rs").exists() && !is_main {
+            "--lib"
+        } else {
+            "--bins"
+        };
+        vec![flag.to_string()]
+    }
+
+    fn args(&self) -> Vec<String> {
+        let (flag, name) = match self {
+            Target::Src => return vec![],
+            Target::Bin(name) => ("--bin", name),
+            Target::Test(name) => ("--test", name),
+            Target::Bench(name) => ("--bench", name),
+            Target::Example(name) => ("--example", name),
+        };
+        vec![flag.to_string(), name.to_string()]
     }
+}
 
-    // ran into an issue where we had path like
-    // /home/meain/dev/src/project/src/blah/blah which caused issues
-    // in the 'src/' split a few lines below
+fn module_name(component: &str) -> String {
+    component.trim_end_matches(".rs").to_string()
+}
+
+/// Find a `#[path = "..."] mod name;` in the files of the same or the
+/// parent directory which points to the given file
+fn find_path_attribute(file: &Path) -> Option<(PathBuf, String)> {
+    let canonical = fs::canonicalize(file).ok()?;
+    let re = Regex::new(r#"#\[path\s*=\s*"([^"]+)"\]\s*(pub(\([^)]*\))?\s+)?mod\s+(\w+)"#).unwrap();
+    let dir = file.parent()?;
+    for d in [Some(dir), dir.parent()].iter().flatten() {
+        let entries = match fs::read_dir(if d.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            d
+        }) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let candidate = d.join(entry.file_name());
+            if candidate.extension().map(|x| x != "rs").unwrap_or(true) || candidate == file {
+                continue;
+            }
+            let text = match fs::read_to_string(&candidate) {
+                Ok(t) => t,
+                Err(_) => continue,
+            };
+            for caps in re.captures_iter(&text) {
+                if fs::canonicalize(d.join(&caps[1])).ok().as_ref() == Some(&canonical) {
+                    return Some((candidate, caps[4].to_string()));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Module path of a file within the target rooted at `target_dir`.
+/// Crate roots map to the empty path and `mod.rs` to its directory.
+fn get_module_path(target_dir: &Path, file: &Path, depth: usize) -> Vec<String> {
+    if depth < 8 {
+        if let Some((parent, name)) = find_path_attribute(file) {
+            let mut module = get_module_path(target_dir, &parent, depth + 1);
+            module.push(name);
+            return module;
+        }
+    }
+
+    let relative = file.strip_prefix(target_dir).unwrap_or(file);
+    let mut module: Vec<String> = relative
+        .iter()
+        .map(|x| module_name(&x.to_string_lossy()))
+        .collect();
+    let is_crate_root = module.len() == 1 && (module[0] == "lib" || module[0] == "main");
+    if is_crate_root || module.last().map(|x| x == "mod") == Some(true) {
+        module.pop();
+    }
+    module
+}
+
+/// Figure out the target of the file and the module path of the file
+/// within that target. Returns `None` if the file is not part of any
+/// target that can be tested.
+fn get_target(filename: &str) -> Option<(Target, Vec<String>)> {
     let root = base::get_project_root(filename, "Cargo.toml");
-    let relative_filename = &if filename.starts_with(root) {
-        root.to_string().push_str("/");
-        filename.replacen(root, "", 1)
-    } else {
-        filename.to_string()
+    let relative_filename = Path::new(filename)
+        .strip_prefix(root)
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_else(|_| filename.to_string());
+    let components: Vec<&str> = relative_filename.split('/').collect();
+
+    let auxiliary = |dir: &str, name: String| match dir {
+        "tests" => Target::Test(name),
+        "benches" => Target::Bench(name),
+        _ => Target::Example(name),
+    };
+    // Targets in `tests/`, `benches/`, `examples/` and `src/bin/` are
+    // either a single file or a directory with a `main.rs`. Other
+    // files in there (like `tests/common/mod.rs`) are not targets.
+    let is_target_dir = |dir: &[&str]| {
+        let mut main = Path::new(root).to_path_buf();
+        main.extend(dir);
+        main.join("main.rs").exists()
+    };
+    let (target, target_dir) = match components.as_slice() {
+        [dir @ ("tests" | "benches" | "examples"), file] => {
+            return Some((auxiliary(dir, module_name(file)), vec![]))
+        }
+        ["src", "bin", file] => return Some((Target::Bin(module_name(file)), vec![])),
+        [dir @ ("tests" | "benches" | "examples"), name, _, ..] => {
+            if !is_target_dir(&components[..2]) {
+                return None;
+            }
+            (auxiliary(dir, name.to_string()), &components[..2])
+        }
+        ["src", "bin", name, _, ..] => {
+            if !is_target_dir(&components[..3]) {
+                return None;
+            }
+            (Target::Bin(name.to_string()), &components[..3])
+        }
+        // ran into an issue where we had path like
+        // /home/meain/dev/src/project/src/blah/blah and so we only
+        // look for `src` after stripping the project root
+        _ => match components.iter().position(|x| *x == "src") {
+            Some(i) => (Target::Src, &components[..i + 1]),
+            None => (Target::Src, &components[..0]),
+        },
     };
-    let file_namespace = if let Some(rfs) = relative_filename.split_once("src/") {
-        rfs.1
-    } else {
-        relative_filename
+
+    let mut dir = Path::new(root).to_path_buf();
+    dir.extend(target_dir);
+    Some((target, get_module_path(&dir, Path::new(filename), 0)))
+}
+
+/// Attributes that mark a function as a test
+const TEST_ATTRIBUTES: &[&str] = &[
+    "test",
+    "tokio::test",
+    "async_std::test",
+    "sqlx::test",
+    "rstest",
+    "test_case",
+];
+/// Attributes that generate multiple tests named after the function
+/// and so cannot be matched with `--exact`
+const PARAMETRIZED_REGEX: &str = r"#\[\s*(?:rstest|test_case)\b";
+const NAMESPACE_REGEX: &str = r"^\s*#\[cfg\(test\)\]";
+
+fn read_manifest(path: &Path) -> Option<toml::Table> {
+    fs::read_to_string(path).ok()?.parse().ok()
+}
+
+/// Package name of the crate and the root of the workspace it is a
+/// member of. Returns `None` if the crate is not part of a workspace
+/// or is the workspace root itself.
+fn get_workspace_package(crate_root: &str) -> Option<(String, String)> {
+    let manifest = read_manifest(&Path::new(crate_root).join("Cargo.toml"))?;
+    if manifest.contains_key("workspace") {
+        return None;
+    }
+    let package = manifest.get("package")?;
+    let name = package.get("name")?.as_str()?.to_string();
+
+    // an explicit `package.workspace` takes precedence over searching
+    if let Some(ws) = package.get("workspace").and_then(|x| x.as_str()) {
+        let ws = Path::new(crate_root).join(ws);
+        return Some((name, ws.to_string_lossy().to_string()));
+    }
+    Path::new(crate_root)
+        .ancestors()
+        .skip(1)
+        .find(|p| {
+            read_manifest(&p.join("Cargo.toml"))
+                .map(|m| m.contains_key("workspace"))
+                .unwrap_or(false)
+        })
+        .map(|p| (name, p.to_string_lossy().to_string()))
+}
+
+/// Root of the workspace the file belongs to, or that of the crate if
+/// it is not part of one
+fn workspace_root(filename: &str) -> Option<String> {
+    let root = base::get_project_root(filename, "Cargo.toml");
+    // the search ends up at `/` for absolute paths outside of any crate
+    if !Path::new(root).join("Cargo.toml").is_file() {
+        return None;
+    }
+    match get_workspace_package(root) {
+        Some((_, ws)) => Some(ws),
+        None => Some(root.to_string()),
+    }
+}
+
+/// Projects using nextest have a config file for it in the workspace
+fn uses_nextest(filename: &str) -> bool {
+    workspace_root(filename)
+        .is_some_and(|root| Path::new(&root).join(".config/nextest.toml").is_file())
+}
+
+/// Tool used to run the tests
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Runner {
+    Cargo,
+    Nextest,
+}
+
+fn new_command(filename: &str, runner: Runner) -> Command {
+    let root = workspace_root(filename).unwrap_or_default();
+    let runner = match runner {
+        Runner::Cargo => "cargo test",
+        Runner::Nextest => "cargo nextest run",
     };
-    let file_namespace = file_namespace.replace("/", "::").replace(".rs", "");
-    match line_no {
-        Some(ln) => {
-            let test_markers = find_nearest_test_markers(&filename, ln)?;
-            let mut comm = format!("cargo test{} {}", verbose_str, file_namespace);
-
-            if let Some(tm) = test_markers {
-                let ns = find_nearest_namespace(filename, tm.namespace[0].no)?;
+    let mut command = Command::new(runner, &root, filename);
+    // filters are module paths and so can be run from the crate root
+    if !root.is_empty() {
+        command.cwd = Some(root);
+    }
+    command
+}
+
+/// Selects the package when the crate is part of a workspace so that
+/// only tests from it are compiled and run
+fn package_args(filename: &str) -> Vec<String> {
+    let root = base::get_project_root(filename, "Cargo.toml");
+    match get_workspace_package(root) {
+        Some((name, _)) => vec!["-p".to_string(), name],
+        None => vec![],
+    }
+}
+
+pub struct CargoTest {
+    attributes: Vec<String>,
+    runner: Runner,
+    /// runner set in the config, detected from the project otherwise
+    framework: Option<Runner>,
+}
+
+impl CargoTest {
+    pub fn new(config: &LanguageConfig) -> CargoTest {
+        let mut attributes: Vec<String> = TEST_ATTRIBUTES.iter().map(|x| x.to_string()).collect();
+        attributes.extend(config.test_attributes.iter().cloned());
+        CargoTest {
+            attributes,
+            runner: Runner::Cargo,
+            framework: config.framework,
+        }
+    }
+
+    /// Picker for projects that run their tests using `cargo nextest`
+    pub fn nextest(config: &LanguageConfig) -> CargoTest {
+        CargoTest {
+            runner: Runner::Nextest,
+            ..CargoTest::new(config)
+        }
+    }
+
+    /// Add the target selection and the filter for the module, namespace
+    /// and test picked in the command
+    fn select(
+        &self,
+        filename: &str,
+        target: &Target,
+        mut command: Command,
+        exact: bool,
+    ) -> Command {
+        command.args.extend(package_args(filename));
+        let mut filter = command.namespace_path();
+        filter.extend(command.test.iter().map(|x| x.name.to_string()));
+        let filter = filter.join("::");
+        match self.runner {
+            Runner::Cargo => {
+                command.args.extend(target.args());
+                if !filter.is_empty() {
+                    command = command.arg(&filter);
+                }
+                // avoid running tests which share the name as a prefix
+                if exact {
+                    command = command.arg("--").arg("--exact");
+                }
+            }
+            Runner::Nextest => {
+                command.args.extend(target.nextest_args(filename));
+                if exact {
+                    command = command.arg("-E").arg(&format!("test(={})", filter));
+                } else if !filter.is_empty() {
+                    command = command.arg("-E").arg(&format!("test(/^{}::/)", filter));
+                }
+            }
+        }
+        command
+    }
+
+    /// Matches any of the test attributes, with or without arguments
+    fn test_regex(&self) -> String {
+        let attributes: Vec<String> = self.attributes.iter().map(|x| regex::escape(x)).collect();
+        format!(r"^\s*#\[\s*(?:{})\s*(?:\(.*)?\]", attributes.join("|"))
+    }
+}
+
+impl Default for CargoTest {
+    fn default() -> CargoTest {
+        CargoTest::new(&LanguageConfig::default())
+    }
+}
+
+impl Picker for CargoTest {
+    fn language(&self) -> &'static str {
+        "rust"
+    }
+
+    fn framework(&self) -> &'static str {
+        match self.runner {
+            Runner::Cargo => "cargo",
+            Runner::Nextest => "nextest",
+        }
+    }
+
+    fn detect(&self, filename: &str) -> bool {
+        let selected = match self.framework {
+            Some(framework) => framework == self.runner,
+            None => self.runner == Runner::Cargo || uses_nextest(filename),
+        };
+        filename.ends_with(".rs") && selected
+    }
+
+    /// Finds the nearest test attribute along with the
+    /// `#[cfg(test)]` marker of the enclosing module
+    fn find_nearest(&self, filename: &str, line_no: usize) -> Result<Option<base::TestCase>> {
+        #[cfg(feature = "tree-sitter")]
+        if let Ok(t) = treesitter::find_nearest(
+            filename,
+            &treesitter::Lang::Rust(self.attributes.clone()),
+            line_no,
+        ) {
+            return Ok(t);
+        }
+        base::find_nearest(
+            filename,
+            &self.test_regex(),
+            Some(NAMESPACE_REGEX),
+            line_no,
+            false,
+        )
+    }
+
+    /// Spans start at the test markers, names are picked up from the
+    /// `mod` or `fn` following them
+    fn find_all(&self, filename: &str) -> Result<Vec<base::TestSpan>> {
+        let mut spans = vec![];
+        for mut span in base::find_all(
+            filename,
+            &self.test_regex(),
+            Some(NAMESPACE_REGEX),
+            base::Block::Braces,
+        )? {
+            let name = match span.scope {
+                Scope::Test => find_nearest_test_function(filename, span.start)?
+                    .map(|n| n.values[1].to_string()),
+                _ => find_nearest_namespace(filename, span.start)?.map(|n| n.values[1].to_string()),
+            };
+            if let Some(name) = name {
+                span.name = name;
+                spans.push(span);
+            }
+        }
+        Ok(spans)
+    }
+
+    fn suite_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        Ok(Some(
+            new_command(filename, self.runner)
+                .scope(Scope::Suite)
+                .verbose(verbose),
+        ))
+    }
+
+    fn file_command(&self, filename: &str, verbose: bool) -> Result<Option<Command>> {
+        let (target, module) = match get_target(filename) {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        let mut command = new_command(filename, self.runner).verbose(verbose);
+        command.module = module;
+        Ok(Some(self.select(filename, &target, command, false)))
+    }
+
+    fn nearest_command(
+        &self,
+        filename: &str,
+        line_no: usize,
+        verbose: bool,
+    ) -> Result<Option<Command>> {
+        let (target, module) = match get_target(filename) {
+            Some(t) => t,
+            None => return Ok(None),
+        };
+        if target == Target::Src {
+            if let Some(item) = find_doctest_item(filename, line_no)? {
+                // nextest does not run doctests
+                let mut command = new_command(filename, Runner::Cargo)
+                    .scope(Scope::Test)
+                    .verbose(verbose);
+                command.args.extend(package_args(filename));
+                command.module = module;
+                command.namespace = base::open_blocks(
+                    filename,
+                    item.line,
+                    &[&format!(r"{}\s*\{{", MOD_REGEX), IMPL_REGEX],
+                )?;
+                command.test = Some(item);
+                let mut filter = command.namespace_path();
+                filter.extend(command.test.iter().map(|x| x.name.to_string()));
+                return Ok(Some(command.arg("--doc").arg(&filter.join("::"))));
+            }
+        }
+
+        let test_markers = self.find_nearest(filename, line_no)?;
+        let mut command = new_command(filename, self.runner).verbose(verbose);
+        command.module = module;
+
+        let mut exact = false;
+        if let Some(tm) = test_markers {
+            if let Some(test_name) = tm.name {
+                let ns = find_nearest_test_function(filename, test_name.no)?;
                 if let Some(n) = ns {
-                    comm = format!("{}::{}", comm, n.values[1]);
+                    // find_next returns zero indexed line numbers
+                    command.namespace = module_chain(filename, n.no + 1)?;
+                    command.test = Some(Location {
+                        name: n.values[1].to_string(),
+                        line: n.no + 1,
+                    });
+                    command.scope = Scope::Test;
+                    exact = !Regex::new(PARAMETRIZED_REGEX)
+                        .unwrap()
+                        .is_match(&test_name.values[0]);
                 } else {
-                    return Err(anyhow!("Could not find mod."));
+                    return Err(anyhow!("Could not find test function."));
                 }
-
-                if let Some(test_name) = tm.name {
-                    let ns = find_nearest_test_function(filename, test_name.no)?;
-                    if let Some(n) = ns {
-                        comm = format!("{}::test_{}", comm, n.values[1]);
-                    } else {
-                        return Err(anyhow!("Could not find test function."));
+            } else if let Some(marker) = tm.namespace.first() {
+                let ns = find_nearest_namespace(filename, marker.no)?;
+                if let Some(n) = ns {
+                    // prefer the innermost module around the cursor
+                    // if we are inside the one with the marker
+                    let mut chain = module_chain(filename, line_no)?;
+                    if !chain.iter().any(|x| x.line == n.no + 1) {
+                        chain = module_chain(filename, n.no + 2)?;
                     }
+                    command.namespace = chain;
+                    command.scope = Scope::Namespace;
+                } else {
+                    return Err(anyhow!("Could not find mod."));
                 }
             }
-
-            return Ok(Some(comm));
-        }
-        None => {
-            let comm = format!("cargo test {}", file_namespace);
-            return Ok(Some(comm));
         }
+
+        Ok(Some(self.select(filename, &target, command, exact)))
     }
 }
 
@@ -94,7 +569,8 @@ mod tests {
 
     #[test]
     fn test_simple_find() {
-        let resp = find_nearest_test_markers("./fixtures/rust/cargo/src/pickers/tester.rs", 16)
+        let resp = CargoTest::default()
+            .find_nearest("./fixtures/rust/cargo/src/pickers/tester.rs", 16)
             .unwrap()
             .unwrap();
         assert_eq!(resp.clone().name.unwrap().no, 5);
@@ -104,68 +580,477 @@ mod tests {
         assert_eq!(resp.namespace[0].values.len(), 1);
     }
 
+    #[test]
+    fn test_find_all() {
+        let resp = CargoTest::default()
+            .find_all("./fixtures/rust/cargo/src/pickers/tester.rs")
+            .unwrap();
+        let resp: Vec<_> = resp
+            .iter()
+            .map(|x| (x.scope, x.name.as_str(), x.start, x.end))
+            .collect();
+        assert_eq!(
+            resp,
+            vec![
+                (Scope::Namespace, "tests", 1, 19),
+                (Scope::Test, "test_simple", 5, 18)
+            ]
+        );
+    }
+
     #[test]
     fn test_simple_command() {
-        let resp = get_command(
-            "./fixtures/rust/cargo/src/pickers/tester.rs",
-            Some(16),
-            false,
-            false,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "cargo test pickers::tester::tests::test_simple");
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/tester.rs",
+                Some(16),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test pickers::tester::tests::test_simple -- --exact"
+        );
+    }
+
+    #[test]
+    fn test_simple_command_locations() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/tester.rs",
+                Some(16),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.scope, Scope::Test);
+        assert_eq!(resp.cwd, Some("./fixtures/rust/cargo".to_string()));
+        assert_eq!(resp.module, vec!["pickers", "tester"]);
+        assert_eq!(resp.namespace[0].name, "tests");
+        assert_eq!(resp.namespace[0].line, 2);
+        assert_eq!(resp.test.unwrap().line, 6);
+    }
+
+    #[test]
+    fn test_integration_test_command() {
+        let resp = CargoTest::default()
+            .get_command("./fixtures/rust/cargo/tests/api.rs", Some(3), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test --test api test_login -- --exact"
+        );
+
+        let resp = CargoTest::default()
+            .get_command("./fixtures/rust/cargo/tests/api.rs", None, false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "cargo test --test api");
+    }
+
+    #[test]
+    fn test_integration_test_dir_command() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/tests/cli/args.rs",
+                Some(3),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test --test cli args::test_flags -- --exact"
+        );
+    }
+
+    #[test]
+    fn test_integration_test_helpers() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/tests/common/mod.rs",
+                Some(1),
+                false,
+                false,
+            )
+            .unwrap();
+        assert!(resp.is_none());
+    }
+
+    #[test]
+    fn test_targets() {
+        assert_eq!(
+            get_target("./fixtures/rust/cargo/benches/parse.rs"),
+            Some((Target::Bench("parse".to_string()), vec![]))
+        );
+        assert_eq!(
+            get_target("./fixtures/rust/cargo/tests/common/mod.rs"),
+            None
+        );
+        assert_eq!(
+            get_target("./fixtures/rust/cargo/tests/cli/main.rs"),
+            Some((Target::Test("cli".to_string()), vec![]))
+        );
+    }
+
+    #[test]
+    fn test_workspace_member_command() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/workspace/crates/parser/src/lexer.rs",
+                Some(6),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test -p parser lexer::tests::test_tokens -- --exact"
+        );
+        assert_eq!(resp.cwd, Some("./fixtures/rust/workspace".to_string()));
+    }
+
+    #[test]
+    fn test_module_paths() {
+        let module = |f: &str| get_target(&format!("./fixtures/rust/cargo/{}", f)).unwrap();
+        assert_eq!(module("src/lib.rs"), (Target::Src, vec![]));
+        assert_eq!(module("src/main.rs"), (Target::Src, vec![]));
+        assert_eq!(
+            module("src/pickers/mod.rs"),
+            (Target::Src, vec!["pickers".to_string()])
+        );
+        assert_eq!(
+            module("src/bin/tool.rs"),
+            (Target::Bin("tool".to_string()), vec![])
+        );
+        assert_eq!(
+            module("src/platform/linux_impl.rs"),
+            (Target::Src, vec!["platform".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_crate_root_command() {
+        let resp = CargoTest::default()
+            .get_command("./fixtures/rust/cargo/src/lib.rs", Some(7), false, false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "cargo test tests::test_lib -- --exact");
+
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/bin/tool.rs",
+                Some(7),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test --bin tool tests::test_tool -- --exact"
+        );
+    }
+
+    #[test]
+    fn test_command_outside_crate() {
+        let dir = std::env::temp_dir().join(format!("toffee-nocargo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("x.rs");
+        std::fs::write(&file, "#[test]\nfn a() {}\n").unwrap();
+        let resp = CargoTest::default()
+            .get_command(file.to_str().unwrap(), Some(2), false, false)
+            .unwrap()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(resp.cwd, None);
+        assert_eq!(resp.root, "");
+        assert!(!resp.shell().starts_with("cd "));
+    }
+
+    #[test]
+    fn test_framework_attributes() {
+        let command = |ln| {
+            CargoTest::default()
+                .get_command(
+                    "./fixtures/rust/cargo/src/pickers/attrs.rs",
+                    Some(ln),
+                    false,
+                    false,
+                )
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            command(7),
+            "cargo test pickers::attrs::tests::handles_timeout -- --exact"
+        );
+        assert_eq!(
+            command(10),
+            "cargo test pickers::attrs::tests::panics_loudly -- --exact"
+        );
+        assert_eq!(command(19), "cargo test pickers::attrs::tests::cases");
+        assert_eq!(command(22), "cargo test pickers::attrs::tests::cases");
+    }
+
+    #[test]
+    fn test_custom_attributes() {
+        let config = LanguageConfig {
+            test_attributes: vec!["my_framework::check".to_string()],
+            ..LanguageConfig::default()
+        };
+        let resp = CargoTest::new(&config)
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/attrs.rs",
+                Some(26),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test pickers::attrs::tests::custom_attribute -- --exact"
+        );
+    }
+
+    #[test]
+    fn test_nested_modules() {
+        let command = |ln| {
+            CargoTest::default()
+                .get_command(
+                    "./fixtures/rust/cargo/src/pickers/nested.rs",
+                    Some(ln),
+                    false,
+                    false,
+                )
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            command(9),
+            "cargo test pickers::nested::parser::tests::parsing::test_parse -- --exact"
+        );
+        assert_eq!(
+            command(17),
+            "cargo test pickers::nested::parser::tests::test_after_nested -- --exact"
+        );
+        assert_eq!(command(5), "cargo test pickers::nested::parser::tests");
+    }
+
+    #[test]
+    fn test_braces_in_multiline_strings() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/strings.rs",
+                Some(10),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test pickers::strings::tests::parses -- --exact"
+        );
+    }
+
+    #[test]
+    fn test_brace_char_literal() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/literals.rs",
+                Some(11),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            resp.to_string(),
+            "cargo test pickers::literals::tests::test_after -- --exact"
+        );
+    }
+
+    #[test]
+    fn test_parametrized_not_exact() {
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/attrs.rs",
+                Some(22),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.args, vec!["pickers::attrs::tests::cases"]);
+    }
+
+    #[test]
+    fn test_nextest_detect() {
+        let nextest = CargoTest::nextest(&LanguageConfig::default());
+        assert!(nextest.detect("./fixtures/rust/nextest/src/lib.rs"));
+        assert!(!nextest.detect("./fixtures/rust/cargo/src/lib.rs"));
+        assert_eq!(nextest.framework(), "nextest");
+
+        // the config takes precedence over the nextest config file
+        let config = |framework| LanguageConfig {
+            framework: Some(framework),
+            ..LanguageConfig::default()
+        };
+        let nextest = CargoTest::nextest(&config(Runner::Nextest));
+        assert!(nextest.detect("./fixtures/rust/cargo/src/lib.rs"));
+        assert!(
+            !CargoTest::new(&config(Runner::Nextest)).detect("./fixtures/rust/cargo/src/lib.rs")
+        );
+        let nextest = CargoTest::nextest(&config(Runner::Cargo));
+        assert!(!nextest.detect("./fixtures/rust/nextest/src/lib.rs"));
+        assert!(CargoTest::new(&config(Runner::Cargo)).detect("./fixtures/rust/nextest/src/lib.rs"));
+    }
+
+    #[test]
+    fn test_nextest_commands() {
+        let nextest = CargoTest::nextest(&LanguageConfig::default());
+        let command = |filename, ln| {
+            nextest
+                .get_command(filename, ln, false, false)
+                .unwrap()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            command("./fixtures/rust/nextest/src/lib.rs", Some(9)),
+            "cargo nextest run --lib -E 'test(=tests::test_run)'"
+        );
+        assert_eq!(
+            command("./fixtures/rust/nextest/src/lib.rs", Some(4)),
+            "cargo nextest run --lib -E 'test(/^tests::/)'"
+        );
+        assert_eq!(
+            command("./fixtures/rust/nextest/tests/smoke.rs", Some(3)),
+            "cargo nextest run --test smoke -E 'test(=test_smoke)'"
+        );
+        assert_eq!(
+            command("./fixtures/rust/nextest/tests/smoke.rs", None),
+            "cargo nextest run --test smoke"
+        );
+        assert_eq!(
+            command(
+                "./fixtures/rust/workspace/crates/parser/src/lexer.rs",
+                Some(6)
+            ),
+            "cargo nextest run -p parser --lib -E 'test(=lexer::tests::test_tokens)'"
+        );
+    }
+
+    #[test]
+    fn test_doctest_command() {
+        let command = |ln| {
+            CargoTest::default()
+                .get_command(
+                    "./fixtures/rust/cargo/src/pickers/docs.rs",
+                    Some(ln),
+                    false,
+                    false,
+                )
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(
+            command(4).to_string(),
+            "cargo test --doc pickers::docs::add"
+        );
+        assert_eq!(
+            command(14).to_string(),
+            "cargo test --doc pickers::docs::Counter::new"
+        );
+        assert_eq!(command(14).test.unwrap().line, 17);
+        assert_eq!(
+            command(22).to_string(),
+            "cargo test --doc pickers::docs::Counter::count"
+        );
+    }
+
+    #[test]
+    fn test_doctest_outside_fence() {
+        assert!(
+            find_doctest_item("./fixtures/rust/cargo/src/pickers/docs.rs", 1)
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            find_doctest_item("./fixtures/rust/cargo/src/pickers/docs.rs", 5)
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            find_doctest_item("./fixtures/rust/cargo/src/pickers/docs.rs", 7)
+                .unwrap()
+                .is_none()
+        );
     }
 
     #[test]
     fn test_mod_command_normal() {
-        let resp = get_command(
-            "./fixtures/rust/cargo/src/pickers/tester.rs",
-            Some(3),
-            false,
-            false,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "cargo test pickers::tester::tests");
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/tester.rs",
+                Some(3),
+                false,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "cargo test pickers::tester::tests");
     }
 
     #[test]
     fn test_full_command_normal() {
-        let resp = get_command(
-            "./fixtures/rust/cargo/src/pickers/tester.rs",
-            Some(3),
-            true,
-            false,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "cargo test");
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/tester.rs",
+                Some(3),
+                true,
+                false,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "cargo test");
     }
 
     #[test]
     fn test_mod_command_verbose() {
-        let resp = get_command(
-            "./fixtures/rust/cargo/src/pickers/tester.rs",
-            Some(3),
-            false,
-            true,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "cargo test -v pickers::tester::tests");
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/tester.rs",
+                Some(3),
+                false,
+                true,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "cargo test -v pickers::tester::tests");
     }
 
     #[test]
     fn test_full_command_verbose() {
-        let resp = get_command(
-            "./fixtures/rust/cargo/src/pickers/tester.rs",
-            Some(3),
-            true,
-            true,
-        )
-        .unwrap()
-        .unwrap();
-        assert_eq!(resp, "cargo test -v");
+        let resp = CargoTest::default()
+            .get_command(
+                "./fixtures/rust/cargo/src/pickers/tester.rs",
+                Some(3),
+                true,
+                true,
+            )
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.to_string(), "cargo test -v");
     }
 }
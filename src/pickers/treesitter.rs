@@ -0,0 +1,387 @@
+use super::base::{TestCase, WithLineNo};
+use super::{Location, Scope};
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::fs;
+use tree_sitter::{Node, Parser, Tree};
+
+#[derive(Debug, Clone)]
+pub enum Lang {
+    /// with the patterns the names of test functions and classes have
+    /// to match
+    Python(String, String),
+    Go,
+    /// with the list of attributes that mark a test
+    Rust(Vec<String>),
+}
+
+/// A test or namespace found in the syntax tree. `start` and `end`
+/// are the lines it covers (including decorators/attributes) and `no`
+/// is the line reported back to the picker.
+#[derive(Debug)]
+struct Item {
+    scope: Scope,
+    no: usize,
+    start: usize,
+    end: usize,
+    values: Vec<String>,
+    children: Vec<Item>,
+}
+
+fn line(node: &Node) -> usize {
+    node.start_position().row + 1
+}
+
+fn text<'a>(node: &Node, source: &'a str) -> &'a str {
+    node.utf8_text(source.as_bytes()).unwrap_or("")
+}
+
+fn line_text(no: usize, source: &str) -> String {
+    source.lines().nth(no - 1).unwrap_or("").trim().to_string()
+}
+
+fn python_items(node: &Node, source: &str, functions: &Regex, classes: &Regex) -> Vec<Item> {
+    let mut items = vec![];
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        // decorators are part of the definition they decorate
+        let (outer, def) = if child.kind() == "decorated_definition" {
+            match child.child_by_field_name("definition") {
+                Some(d) => (child, d),
+                None => continue,
+            }
+        } else {
+            (child, child)
+        };
+        let name = match def.child_by_field_name("name") {
+            Some(n) => text(&n, source).to_string(),
+            None => continue,
+        };
+        let no = line(&def);
+        let mut values = vec![line_text(no, source)];
+        match def.kind() {
+            "class_definition" if classes.is_match(&name) => {
+                values.push(name);
+                items.push(Item {
+                    scope: Scope::Namespace,
+                    no,
+                    start: line(&outer),
+                    end: outer.end_position().row + 1,
+                    values,
+                    children: match def.child_by_field_name("body") {
+                        Some(body) => python_items(&body, source, functions, classes),
+                        None => vec![],
+                    },
+                });
+            }
+            "function_definition" if functions.is_match(&name) => {
+                if def.child(0).map(|x| x.kind()) == Some("async") {
+                    values.push("async ".to_string());
+                }
+                values.push(name);
+                items.push(Item {
+                    scope: Scope::Test,
+                    no,
+                    start: line(&outer),
+                    end: outer.end_position().row + 1,
+                    values,
+                    children: vec![],
+                });
+            }
+            _ => {}
+        }
+    }
+    items
+}
+
+fn go_items(node: &Node, source: &str) -> Vec<Item> {
+    let mut items = vec![];
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        // methods are tests in testify suites, values also hold the
+        // suite type for them
+        let receiver = match child.kind() {
+            "function_declaration" => None,
+            "method_declaration" => match child.child_by_field_name("receiver") {
+                Some(r) => text(&r, source)
+                    .trim_matches(|c| c == '(' || c == ')')
+                    .split_whitespace()
+                    .last()
+                    .map(|x| x.trim_start_matches('*').to_string()),
+                None => continue,
+            },
+            _ => continue,
+        };
+        if let Some(n) = child.child_by_field_name("name") {
+            let name = text(&n, source);
+            let is_test = match receiver {
+                Some(_) => name.starts_with("Test"),
+                None => ["Test", "Example", "Benchmark", "Fuzz"]
+                    .iter()
+                    .any(|x| name.starts_with(x)),
+            };
+            if is_test {
+                let no = line(&child);
+                let mut values = vec![line_text(no, source)];
+                values.extend(receiver);
+                values.push(name.to_string());
+                items.push(Item {
+                    scope: Scope::Test,
+                    no,
+                    start: no,
+                    end: child.end_position().row + 1,
+                    values,
+                    children: vec![],
+                })
+            }
+        }
+    }
+    items
+}
+
+/// Attributes are siblings of the item they are attached to
+fn rust_attributes<'a>(node: &Node<'a>, source: &str) -> Vec<(Node<'a>, String)> {
+    let mut attributes = vec![];
+    let mut prev = node.prev_named_sibling();
+    while let Some(p) = prev {
+        match p.kind() {
+            "attribute_item" => {
+                let attr: String = text(&p, source)
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect();
+                attributes.push((p, attr));
+            }
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        prev = p.prev_named_sibling();
+    }
+    attributes.reverse();
+    attributes
+}
+
+/// Path of the attribute without the arguments, `test` for `#[test]`
+fn attribute_path(attr: &str) -> &str {
+    let attr = attr.trim_start_matches("#[").trim_end_matches(']');
+    attr.split('(').next().unwrap_or(attr)
+}
+
+fn rust_items(node: &Node, source: &str, test_attributes: &[String]) -> Vec<Item> {
+    let mut items = vec![];
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let scope = match child.kind() {
+            "function_item" => Scope::Test,
+            "mod_item" => Scope::Namespace,
+            _ => continue,
+        };
+        let attributes = rust_attributes(&child, source);
+        let children = match child.child_by_field_name("body") {
+            Some(body) if scope == Scope::Namespace => rust_items(&body, source, test_attributes),
+            _ => vec![],
+        };
+        let marker = attributes.iter().find(|(_, a)| match scope {
+            Scope::Test => test_attributes.iter().any(|t| t == attribute_path(a)),
+            _ => a == "#[cfg(test)]",
+        });
+        match marker {
+            Some((attr, _)) => {
+                let no = line(attr);
+                items.push(Item {
+                    scope,
+                    no,
+                    start: attributes.first().map(|(a, _)| line(a)).unwrap_or(no),
+                    end: child.end_position().row + 1,
+                    values: vec![line_text(no, source)],
+                    children,
+                })
+            }
+            // tests in modules without the marker belong to the parent
+            None => items.extend(children),
+        }
+    }
+    items
+}
+
+/// Path from the outermost item to the innermost one containing the
+/// line. If no item contains the line, the last item defined above it
+/// is used instead.
+fn resolve(items: &[Item], line_no: usize) -> Vec<&Item> {
+    let mut path = vec![];
+    let mut current = items;
+    while let Some(item) = current
+        .iter()
+        .find(|x| x.start <= line_no && line_no <= x.end)
+    {
+        path.push(item);
+        current = &item.children;
+    }
+    if !path.is_empty() {
+        return path;
+    }
+
+    fn last_above<'a>(items: &'a [Item], line_no: usize, path: &mut Vec<&'a Item>) {
+        if let Some(item) = items.iter().rev().find(|x| x.start <= line_no) {
+            path.push(item);
+            last_above(&item.children, line_no, path);
+        }
+    }
+    last_above(items, line_no, &mut path);
+    path
+}
+
+/// Source and syntax tree of the file, erroring out if it has syntax
+/// errors
+fn parse(filename: &str, lang: &Lang) -> Result<(String, Tree)> {
+    let source = fs::read_to_string(filename)?;
+    let mut parser = Parser::new();
+    let language = match lang {
+        Lang::Python(_, _) => tree_sitter_python::LANGUAGE,
+        Lang::Go => tree_sitter_go::LANGUAGE,
+        Lang::Rust(_) => tree_sitter_rust::LANGUAGE,
+    };
+    parser.set_language(&language.into())?;
+    let tree = parser
+        .parse(&source, None)
+        .ok_or_else(|| anyhow!("Unable to parse {}", filename))?;
+    if tree.root_node().has_error() {
+        return Err(anyhow!("Syntax errors in {}", filename));
+    }
+    Ok((source, tree))
+}
+
+/// Inline rust modules still open at the start of the (1 indexed)
+/// line, outermost first
+pub fn rust_modules(filename: &str, line_no: usize) -> Result<Vec<Location>> {
+    let (source, tree) = parse(filename, &Lang::Rust(vec![]))?;
+    let mut modules = vec![];
+    let mut node = tree.root_node();
+    'outer: loop {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            let inside = line(&child) < line_no && child.end_position().row + 1 >= line_no;
+            if !inside {
+                continue;
+            }
+            let name = child.child_by_field_name("name");
+            let body = child.child_by_field_name("body");
+            if let (true, Some(name), Some(body)) = (child.kind() == "mod_item", name, body) {
+                modules.push(Location {
+                    name: text(&name, &source).to_string(),
+                    line: line(&child),
+                });
+                node = body;
+                continue 'outer;
+            }
+        }
+        return Ok(modules);
+    }
+}
+
+/// Find the test/namespace enclosing the line using a syntax tree.
+/// Returns an error if the file could not be parsed cleanly, in which
+/// case the regex based `base::find_nearest` should be used instead.
+pub fn find_nearest(filename: &str, lang: &Lang, line_no: usize) -> Result<Option<TestCase>> {
+    let (source, tree) = parse(filename, lang)?;
+    let root = tree.root_node();
+
+    let items = match lang {
+        Lang::Python(functions, classes) => {
+            let full = |x: &str| Regex::new(&format!("^(?:{})$", x));
+            python_items(&root, &source, &full(functions)?, &full(classes)?)
+        }
+        Lang::Go => go_items(&root, &source),
+        Lang::Rust(attributes) => rust_items(&root, &source, attributes),
+    };
+    let path = resolve(&items, line_no);
+    if path.is_empty() {
+        return Ok(None);
+    }
+
+    let mut test_case = TestCase {
+        name: None,
+        namespace: vec![],
+    };
+    for item in path {
+        let wl = WithLineNo {
+            no: item.no,
+            values: item.values.clone(),
+        };
+        match item.scope {
+            Scope::Test => test_case.name = Some(wl),
+            _ => test_case.namespace.push(wl),
+        }
+    }
+    Ok(Some(test_case))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn python() -> Lang {
+        Lang::Python(r"test_\w+".to_string(), r"\w+".to_string())
+    }
+
+    #[test]
+    fn test_python_multiline_and_decorators() {
+        let resp = find_nearest("./fixtures/python/pytest/test_syntax.py", &python(), 8)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.clone().name.unwrap().no, 10);
+        assert_eq!(resp.name.unwrap().values[1], "test_decorated");
+        assert_eq!(resp.namespace[0].values[1], "TestSyntax");
+    }
+
+    #[test]
+    fn test_python_comment_is_not_a_test() {
+        let resp = find_nearest("./fixtures/python/pytest/test_syntax.py", &python(), 19)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.clone().name.unwrap().no, 14);
+        assert_eq!(resp.name.unwrap().values[1], "test_multiline");
+    }
+
+    #[test]
+    fn test_rust_test_in_mod() {
+        let resp = find_nearest(
+            "./fixtures/rust/cargo/src/pickers/tester.rs",
+            &Lang::Rust(vec!["test".to_string()]),
+            16,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(resp.clone().name.unwrap().no, 5);
+        assert_eq!(resp.namespace[0].no, 1);
+    }
+
+    #[test]
+    fn test_rust_modules() {
+        let names = |filename, ln| -> Vec<String> {
+            rust_modules(filename, ln)
+                .unwrap()
+                .into_iter()
+                .map(|x| x.name)
+                .collect()
+        };
+        assert_eq!(
+            names("./fixtures/rust/cargo/src/pickers/nested.rs", 9),
+            vec!["parser", "tests", "parsing"]
+        );
+        assert_eq!(
+            names("./fixtures/rust/cargo/src/pickers/nested.rs", 17),
+            vec!["parser", "tests"]
+        );
+        assert_eq!(
+            names("./fixtures/rust/cargo/src/pickers/strings.rs", 10),
+            vec!["tests"]
+        );
+    }
+
+    #[test]
+    fn test_syntax_error() {
+        assert!(find_nearest("./fixtures/python/pytest/test_broken.py", &python(), 2).is_err());
+    }
+}
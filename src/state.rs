@@ -0,0 +1,144 @@
+use crate::pickers::Command;
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Last command that was generated for each project, persisted in the
+/// cache dir so that it can be rerun from anywhere in the project.
+pub struct State {
+    path: PathBuf,
+    last: BTreeMap<String, Command>,
+}
+
+fn absolute(path: &str) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| match env::current_dir() {
+        Ok(cwd) => cwd.join(path),
+        Err(_) => PathBuf::from(path),
+    })
+}
+
+impl State {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|d| d.join("toffee").join("last.json"))
+    }
+
+    pub fn load(path: &Path) -> Result<State> {
+        let last = if path.exists() {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Unable to read {}", path.display()))?;
+            // start over on a corrupted file, the next save replaces it
+            serde_json::from_str(&text).unwrap_or_else(|e| {
+                eprintln!("Warning: Unable to parse {}: {}", path.display(), e);
+                BTreeMap::new()
+            })
+        } else {
+            BTreeMap::new()
+        };
+        Ok(State {
+            path: path.to_path_buf(),
+            last,
+        })
+    }
+
+    /// Remember the command as the last one run in its project
+    pub fn save(&mut self, command: &Command) -> Result<()> {
+        let root = if command.root.is_empty() {
+            absolute(".")
+        } else {
+            absolute(&command.root)
+        };
+        let mut command = command.clone();
+        // relative paths in the command are relative to where it was
+        // generated from, so store the absolute version of it
+        command.cwd = Some(
+            absolute(&command.working_dir())
+                .to_string_lossy()
+                .to_string(),
+        );
+        self.last
+            .insert(root.to_string_lossy().to_string(), command);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create {}", parent.display()))?;
+        }
+        fs::write(&self.path, serde_json::to_string(&self.last)?)
+            .with_context(|| format!("Unable to write {}", self.path.display()))
+    }
+
+    /// Last command from the innermost project containing the file
+    pub fn last(&self, filename: &str) -> Option<&Command> {
+        let path = absolute(filename);
+        self.last
+            .iter()
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.len())
+            .map(|(_, command)| command)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pickers::Scope;
+
+    #[test]
+    fn test_save_and_load_last() {
+        let path = env::temp_dir().join(format!("toffee-state-{}.json", std::process::id()));
+        let mut state = State::load(&path).unwrap();
+        let command = Command::new("cargo test", "./fixtures/rust/cargo", "")
+            .arg("pickers::tester::tests::test_simple");
+        state.save(&command).unwrap();
+        state
+            .save(&Command::new("go test", "./fixtures/go", "").arg("./..."))
+            .unwrap();
+
+        let state = State::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let last = state.last("./fixtures/rust/cargo/Cargo.toml").unwrap();
+        assert_eq!(
+            last.to_string(),
+            "cargo test pickers::tester::tests::test_simple"
+        );
+        assert_eq!(
+            last.cwd,
+            Some(env::current_dir().unwrap().to_string_lossy().to_string())
+        );
+        assert_eq!(
+            state
+                .last("./fixtures/go/gotest/main_test.go")
+                .unwrap()
+                .to_string(),
+            "go test ./..."
+        );
+        assert!(state
+            .last("./fixtures/python/pytest/test_stuff.py")
+            .is_none());
+    }
+
+    #[test]
+    fn test_load_corrupted_state() {
+        let path = env::temp_dir().join(format!("toffee-broken-{}.json", std::process::id()));
+        fs::write(&path, "{\"/tmp\": ").unwrap();
+        let mut state = State::load(&path).unwrap();
+        assert!(state.last("/tmp/test_stuff.py").is_none());
+        state
+            .save(&Command::new("go test", "./fixtures/go", "").arg("./..."))
+            .unwrap();
+        assert!(State::load(&path)
+            .unwrap()
+            .last("./fixtures/go/gotest/main_test.go")
+            .is_some());
+
+        // fields missing from older versions get their defaults
+        fs::write(&path, r#"{"/tmp": {"runner": ["pytest"], "root": "/tmp"}}"#).unwrap();
+        let state = State::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let last = state.last("/tmp/test_stuff.py").unwrap();
+        assert_eq!(last.to_string(), "pytest");
+        assert_eq!(last.scope, Scope::Suite);
+    }
+}